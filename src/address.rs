@@ -0,0 +1,159 @@
+use std::net::Ipv6Addr;
+
+use url::Url;
+
+
+/// The port the SpotifyPi server listens on.
+pub const DEFAULT_PORT: u16 = 9487;
+
+/// Turns what the user typed into a WebSocket URL. Accepts `host`,
+/// `host:port`, `[ipv6]:port`, a bare IPv6 address and full `ws://` or
+/// `wss://` URLs with a path and query. Without a port, `DEFAULT_PORT` is
+/// used. The error is a short message fit to show next to the input.
+pub fn parse_address(input: &str) -> Result<Url, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Enter an address".to_string());
+    }
+
+    let (scheme, rest) = match input.split_once("://") {
+        Some((scheme, rest)) => (scheme.to_ascii_lowercase(), rest),
+        None => ("ws".to_string(), input),
+    };
+    if scheme != "ws" && scheme != "wss" {
+        return Err("Use ws:// or wss://".to_string());
+    }
+
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(authority_end);
+    if authority.contains('@') {
+        return Err("User names are not supported".to_string());
+    }
+
+    let (host, port) = split_host_port(authority)?;
+    let port = parse_port(port, DEFAULT_PORT)?;
+    if host.is_empty() {
+        return Err("Missing host name".to_string());
+    }
+
+    Url::parse(&format!("{}://{}:{}{}", scheme, host, port, path))
+        .map_err(|_| "Invalid host name".to_string())
+}
+
+/// Splits `host`, `host:port`, `[ipv6]:port` or a bare IPv6 address the
+/// way `parse_address` does, for plain TCP services such as an MQTT broker.
+/// IPv6 hosts come back without brackets.
+pub fn parse_host_port(input: &str, default_port: u16) -> Result<(String, u16), String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Enter an address".to_string());
+    }
+    let (host, port) = split_host_port(input)?;
+    let port = parse_port(port, default_port)?;
+    if host.is_empty() {
+        return Err("Missing host name".to_string());
+    }
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_string();
+    Ok((host, port))
+}
+
+fn parse_port(port: Option<&str>, default_port: u16) -> Result<u16, String> {
+    match port {
+        Some(port) => port.parse::<u16>()
+            .ok()
+            .filter(|port| *port != 0)
+            .ok_or_else(|| "Port must be 1 to 65535".to_string()),
+        None => Ok(default_port),
+    }
+}
+
+/// Splits `authority` into the host, IPv6 addresses in brackets, and the
+/// port text if there is one.
+fn split_host_port(authority: &str) -> Result<(String, Option<&str>), String> {
+    if let Some(rest) = authority.strip_prefix('[') {
+        let (ip, after) = rest.split_once(']')
+            .ok_or_else(|| "Missing ] after IPv6 address".to_string())?;
+        ip.parse::<Ipv6Addr>().map_err(|_| "Invalid IPv6 address".to_string())?;
+        let port = match after {
+            "" => None,
+            _ => Some(after.strip_prefix(':').ok_or_else(|| "Invalid text after ]".to_string())?),
+        };
+        return Ok((format!("[{}]", ip), port));
+    }
+    // More than one colon: an IPv6 address without brackets, so no port.
+    if authority.matches(':').count() > 1 {
+        let ip = authority.parse::<Ipv6Addr>()
+            .map_err(|_| "Put IPv6 addresses in [ ]".to_string())?;
+        return Ok((format!("[{}]", ip), None));
+    }
+    match authority.split_once(':') {
+        Some((host, port)) => Ok((host.to_string(), Some(port))),
+        None => Ok((authority.to_string(), None)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(input: &str) -> String {
+        parse_address(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn host_gets_default_port() {
+        assert_eq!(url("spotifypi.local"), "ws://spotifypi.local:9487/");
+        assert_eq!(url("  192.168.1.20 "), "ws://192.168.1.20:9487/");
+    }
+
+    #[test]
+    fn host_with_port() {
+        assert_eq!(url("spotifypi.local:8080"), "ws://spotifypi.local:8080/");
+    }
+
+    #[test]
+    fn ipv6_with_port() {
+        assert_eq!(url("[fe80::1]:8080"), "ws://[fe80::1]:8080/");
+        assert_eq!(url("[::1]"), "ws://[::1]:9487/");
+    }
+
+    #[test]
+    fn bare_ipv6_gets_brackets() {
+        assert_eq!(url("fe80::1"), "ws://[fe80::1]:9487/");
+    }
+
+    #[test]
+    fn ws_urls_keep_path_and_query() {
+        assert_eq!(url("ws://pi:9000/socket?x=1"), "ws://pi:9000/socket?x=1");
+        assert_eq!(url("WSS://pi/socket"), "wss://pi:9487/socket");
+    }
+
+    #[test]
+    fn rejects_bad_ports() {
+        assert!(parse_address("pi:0").is_err());
+        assert!(parse_address("pi:65536").is_err());
+        assert!(parse_address("pi:").is_err());
+        assert_eq!(url("pi:65535"), "ws://pi:65535/");
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(parse_address("").is_err());
+        assert!(parse_address("   ").is_err());
+        assert!(parse_address("user@pi").is_err());
+        assert!(parse_address("ws://user@pi:9487").is_err());
+        assert!(parse_address("[fe80::1:8080").is_err());
+        assert!(parse_address("http://pi").is_err());
+        assert!(parse_address(":9487").is_err());
+    }
+
+    #[test]
+    fn host_port_for_tcp() {
+        assert_eq!(parse_host_port("broker", 1883), Ok(("broker".to_string(), 1883)));
+        assert_eq!(parse_host_port("broker:1884", 1883), Ok(("broker".to_string(), 1884)));
+        assert_eq!(parse_host_port("[::1]:1884", 1883), Ok(("::1".to_string(), 1884)));
+        assert_eq!(parse_host_port("::1", 1883), Ok(("::1".to_string(), 1883)));
+        assert!(parse_host_port("broker:0", 1883).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use crate::my_enums::AlbumArt;
+
+
+/// Oldest images are removed once the cache grows past this.
+const MAX_CACHE_BYTES: u64 = 50 * 1024 * 1024;
+/// Gives up on an image server that stalls, so the next track's art loads.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Returns the path of the cached image file for `art`, downloading or
+/// writing it first if it is not in the cache yet.
+pub async fn load_album_art(art: AlbumArt) -> Option<PathBuf> {
+    let path = cache_path(&art)?;
+    if path.exists() {
+        // Marks the image as recently used for pruning.
+        if let Ok(file) = std::fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        return Some(path);
+    }
+
+    let bytes = match art {
+        AlbumArt::Url(url) => fetch_album_art(&url).await?,
+        AlbumArt::Inline(bytes) => bytes,
+    };
+
+    if let Some(dir) = path.parent() {
+        if let Err(e) = tokio::fs::create_dir_all(dir).await {
+            warn!("Failed to create album art cache dir: {}", e);
+            return None;
+        }
+    }
+    // Written next to the file and renamed over it, so a crash cannot leave
+    // a truncated image in the cache.
+    let tmp_path = path.with_extension("tmp");
+    let written = match tokio::fs::write(&tmp_path, bytes).await {
+        Ok(()) => tokio::fs::rename(&tmp_path, &path).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = written {
+        warn!("Failed to write album art cache: {}", e);
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        return None;
+    }
+    if let Some(dir) = path.parent().map(Path::to_path_buf) {
+        let _ = tokio::task::spawn_blocking(move || prune_cache(&dir, MAX_CACHE_BYTES)).await;
+    }
+    Some(path)
+}
+
+/// Removes the least recently used images until the cache fits in
+/// `max_bytes`.
+fn prune_cache(dir: &Path, max_bytes: u64) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata.is_file().then(|| (metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH), metadata.len(), entry.path()))
+        })
+        .collect();
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    files.sort();
+    for (_, len, path) in files {
+        if total <= max_bytes {
+            break;
+        }
+        debug!("Removing cached album art {}", path.display());
+        if std::fs::remove_file(&path).is_ok() {
+            total -= len;
+        }
+    }
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+async fn fetch_album_art(url: &str) -> Option<Vec<u8>> {
+    let response = match http_client().get(url).send().await.and_then(|r| r.error_for_status()) {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Failed to fetch album art: {}", e);
+            return None;
+        }
+    };
+    match response.bytes().await {
+        Ok(bytes) => Some(bytes.to_vec()),
+        Err(e) => {
+            warn!("Failed to read album art: {}", e);
+            None
+        }
+    }
+}
+
+/// The file name is a SHA-256 of the URL or image data, so it stays the
+/// same across builds.
+fn cache_path(art: &AlbumArt) -> Option<PathBuf> {
+    let digest = match art {
+        AlbumArt::Url(url) => Sha256::digest(url.as_bytes()),
+        AlbumArt::Inline(bytes) => Sha256::digest(bytes),
+    };
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let file_name = format!("{}.img", hex);
+    dirs::cache_dir().map(|dir| dir.join("spotifypi-control-panel-fl").join("album_art").join(file_name))
+}
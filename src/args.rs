@@ -0,0 +1,28 @@
+use clap::Parser;
+
+use crate::theme::Theme;
+
+
+#[derive(Parser)]
+#[clap(name = "spotifypi-control-panel-fl", version, about = "Desktop control panel for SpotifyPi")]
+pub struct Args {
+    /// SpotifyPi address or profile nickname, instead of the last one used
+    #[clap(long)]
+    pub address: Option<String>,
+
+    /// Connect right after starting
+    #[clap(long)]
+    pub connect: bool,
+
+    /// Theme for this run; the saved theme is kept
+    #[clap(long, possible_values = ["fluent", "aqua", "gleam", "dark"])]
+    pub theme: Option<Theme>,
+
+    /// Compact window with only the address and playback rows
+    #[clap(long)]
+    pub mini: bool,
+
+    /// Log debug messages, including every received frame
+    #[clap(short, long)]
+    pub verbose: bool,
+}
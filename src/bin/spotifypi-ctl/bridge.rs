@@ -0,0 +1,170 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use futures::stream::{self, Stream};
+use serde::Deserialize;
+use serde_json::json;
+use subtle::ConstantTimeEq;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::Duration;
+
+use spotifypi_control_panel_fl::commands::Command;
+use spotifypi_control_panel_fl::my_enums::CommandResult;
+use spotifypi_control_panel_fl::player_state::PlayerState;
+
+use crate::supervisor::Supervisor;
+
+
+struct Bridge {
+    supervisor: Arc<Supervisor>,
+    token: Option<String>,
+    /// Whether `shutdown` and `reboot` are accepted.
+    allow_power: bool,
+}
+
+#[derive(Deserialize)]
+struct VolumeBody {
+    volume: u32,
+}
+
+/// Serves the REST bridge on `bind` until the process is stopped.
+///
+/// `POST /<action>` sends a command, `PUT /volume` takes `{"volume": n}`,
+/// `GET /state` returns the last known state and `GET /events` streams it
+/// as server-sent events. With a `token`, every request needs either an
+/// `Authorization: Bearer <token>` header or a `?token=<token>` query.
+/// Without one, only loopback addresses may be bound. Power actions are
+/// refused unless `allow_power`.
+pub async fn run_bridge(url: url::Url, connect_timeout: Duration, bind: SocketAddr, token: Option<String>, allow_power: bool) -> Result<(), String> {
+    if token.is_none() && !bind.ip().is_loopback() {
+        return Err(format!("Refusing to listen on {} without a token: anyone on the network could control the Pi", bind));
+    }
+    let bridge = Arc::new(Bridge {
+        supervisor: Arc::new(Supervisor::new()),
+        token,
+        allow_power,
+    });
+
+    let app = Router::new()
+        .route("/state", get(get_state))
+        .route("/events", get(get_events))
+        .route("/volume", put(put_volume))
+        .route("/:action", post(post_action))
+        .route_layer(middleware::from_fn_with_state(bridge.clone(), check_token))
+        .with_state(bridge.clone());
+
+    let server = axum::Server::try_bind(&bind)
+        .map_err(|e| format!("Failed to bind {}: {}", bind, e))?;
+    tokio::spawn(bridge.supervisor.clone().run(url, connect_timeout));
+    server.serve(app.into_make_service())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+async fn check_token<B>(State(bridge): State<Arc<Bridge>>, request: Request<B>, next: Next<B>) -> Response {
+    let token = match &bridge.token {
+        Some(token) => token,
+        None => return next.run(request).await,
+    };
+    let bearer = request.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let query = request.uri()
+        .query()
+        .and_then(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "token")
+                .map(|(_, value)| value.into_owned())
+        });
+    if bearer.is_some_and(|bearer| token_matches(bearer, token))
+        || query.is_some_and(|query| token_matches(&query, token))
+    {
+        next.run(request).await
+    } else {
+        (StatusCode::UNAUTHORIZED, Json(json!({ "error": "invalid token" }))).into_response()
+    }
+}
+
+/// Compares in constant time, so the token cannot be guessed from how
+/// long a rejection takes.
+fn token_matches(given: &str, token: &str) -> bool {
+    given.as_bytes().ct_eq(token.as_bytes()).into()
+}
+
+async fn get_state(State(bridge): State<Arc<Bridge>>) -> Response {
+    Json(bridge.supervisor.state()).into_response()
+}
+
+async fn get_events(State(bridge): State<Arc<Bridge>>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let initial = bridge.supervisor.state();
+    let updates = bridge.supervisor.subscribe_state();
+    let stream = stream::unfold((Some(initial), updates), |(initial, mut updates)| async move {
+        if let Some(state) = initial {
+            return Some((Ok(state_event(&state)), (None, updates)));
+        }
+        loop {
+            match updates.recv().await {
+                Ok(state) => return Some((Ok(state_event(&state)), (None, updates))),
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn state_event(state: &PlayerState) -> Event {
+    Event::default()
+        .event("state")
+        .data(serde_json::to_string(state).unwrap_or_default())
+}
+
+async fn put_volume(State(bridge): State<Arc<Bridge>>, Json(body): Json<VolumeBody>) -> Response {
+    match Command::parse(&format!("set_volume {}", body.volume)) {
+        Ok(command) => send_command(&bridge, command).await,
+        Err(e) => (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))).into_response(),
+    }
+}
+
+async fn post_action(State(bridge): State<Arc<Bridge>>, Path(action): Path<String>) -> Response {
+    let command = match action.as_str() {
+        "prev" => Command::PrevTrack,
+        "next" => Command::NextTrack,
+        "play-pause" => Command::TogglePlayPause,
+        "play" => Command::Play,
+        "pause" => Command::Pause,
+        "shuffle" => Command::ToggleShuffle,
+        "repeat" => Command::ToggleRepeatState,
+        "save" => Command::SaveTrack,
+        "unsave" => Command::RemoveSavedTrack,
+        "shutdown" => Command::Shutdown,
+        "reboot" => Command::Reboot,
+        _ => return (StatusCode::NOT_FOUND, Json(json!({ "error": "unknown action" }))).into_response(),
+    };
+    if matches!(command, Command::Shutdown | Command::Reboot) && !bridge.allow_power {
+        return (StatusCode::FORBIDDEN, Json(json!({ "command": command.name(), "result": "forbidden" }))).into_response();
+    }
+    send_command(&bridge, command).await
+}
+
+async fn send_command(bridge: &Bridge, command: Command) -> Response {
+    let result = bridge.supervisor.send_command(&command).await;
+    let status = match result {
+        CommandResult::Ok => StatusCode::OK,
+        CommandResult::Failed => StatusCode::BAD_GATEWAY,
+        CommandResult::Missing => StatusCode::BAD_REQUEST,
+        CommandResult::NotFound => StatusCode::NOT_IMPLEMENTED,
+        CommandResult::Timeout => StatusCode::GATEWAY_TIMEOUT,
+        CommandResult::Disconnected => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(json!({ "command": command.name(), "result": result.as_str() }))).into_response()
+}
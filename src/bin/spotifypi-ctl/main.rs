@@ -0,0 +1,339 @@
+mod bridge;
+mod mqtt;
+mod shell;
+mod supervisor;
+mod watch;
+
+use std::net::SocketAddr;
+use std::process::exit;
+
+use clap::{Parser, Subcommand};
+use serde_json::json;
+use tokio::time::Duration;
+use tracing::Level;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+
+use spotifypi_control_panel_fl::address::{parse_address, parse_host_port};
+use spotifypi_control_panel_fl::commands::Command;
+use spotifypi_control_panel_fl::my_enums::{CommandResult, ConnectError, WSEventValue};
+use spotifypi_control_panel_fl::ws::WsSession;
+
+
+const EXIT_FAILED: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_MISSING: i32 = 3;
+const EXIT_NOT_FOUND: i32 = 4;
+const EXIT_TIMEOUT: i32 = 5;
+const EXIT_CONNECT: i32 = 6;
+
+const EXIT_CODES_HELP: &str = "EXIT CODES:
+    0    Success
+    1    The server reported a failure
+    2    Invalid arguments
+    3    The server reported a missing argument
+    4    The server did not recognise the command
+    5    Timed out waiting for the connection or reply
+    6    Could not connect, or the connection was lost";
+
+#[derive(Parser)]
+#[clap(name = "spotifypi-ctl", version, about = "Command-line client for SpotifyPi", after_help = EXIT_CODES_HELP)]
+struct Cli {
+    /// SpotifyPi address: host, host:port, [ipv6]:port or a ws:// or wss:// URL
+    #[clap(long, default_value = "spotifypi.local:9487")]
+    host: String,
+
+    /// Print the result as a JSON object
+    #[clap(long)]
+    json: bool,
+
+    /// Connect timeout in seconds
+    #[clap(long, default_value_t = 5)]
+    timeout: u64,
+
+    /// Log connection details and debug messages to stderr
+    #[clap(short, long)]
+    verbose: bool,
+
+    #[clap(subcommand)]
+    action: Action,
+}
+
+#[derive(Subcommand)]
+enum Action {
+    /// Previous track
+    Prev,
+    /// Next track
+    Next,
+    /// Toggle play / pause
+    PlayPause,
+    /// Resume playback
+    Play,
+    /// Pause playback
+    Pause,
+    /// Toggle shuffle
+    Shuffle,
+    /// Cycle repeat off / single song / whole playlist
+    Repeat,
+    /// Get or set the volume
+    Volume {
+        #[clap(subcommand)]
+        action: VolumeAction,
+    },
+    /// Show the current track
+    NowPlaying,
+    /// Save the current track to the library
+    Save,
+    /// Remove the current track from the library
+    Unsave,
+    /// Start playing a playlist
+    PlayPlaylist {
+        /// Spotify playlist URI
+        uri: String,
+    },
+    /// Shut down the Pi
+    Shutdown,
+    /// Reboot the Pi
+    Reboot,
+    /// Interactive shell printing events as they arrive
+    Shell,
+    /// Print every event as a JSON line, reconnecting when the connection drops
+    Watch,
+    /// Serve a local HTTP API over one persistent connection
+    Bridge {
+        /// Address to listen on
+        #[clap(long, default_value = "127.0.0.1:8080")]
+        bind: SocketAddr,
+
+        /// Require this token as a Bearer header or `token` query parameter
+        #[clap(long, env = "SPOTIFYPI_BRIDGE_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+
+        /// Also accept shutdown and reboot
+        #[clap(long)]
+        allow_power: bool,
+    },
+    /// Mirror the state to an MQTT broker, with Home Assistant discovery
+    Mqtt {
+        /// Broker address: host, host:port or [ipv6]:port
+        #[clap(long, default_value = "localhost:1883")]
+        broker: String,
+
+        /// Also accept shutdown and reboot on the command topic
+        #[clap(long)]
+        allow_power: bool,
+
+        /// Broker user name
+        #[clap(long, requires = "password")]
+        username: Option<String>,
+
+        /// Broker password
+        #[clap(long, env = "SPOTIFYPI_MQTT_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+
+        /// Prefix of the state and command topics
+        #[clap(long, default_value = "spotifypi")]
+        topic: String,
+
+        /// Home Assistant discovery prefix
+        #[clap(long, default_value = "homeassistant")]
+        discovery_prefix: String,
+
+        /// Device id used in discovery topics and unique ids
+        #[clap(long, default_value = "spotifypi")]
+        node_id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum VolumeAction {
+    /// Print the current volume
+    Get,
+    /// Set the volume (0-100)
+    Set {
+        #[clap(value_parser = clap::value_parser!(u32).range(0..=100))]
+        volume: u32,
+    },
+}
+
+impl Action {
+    fn command(&self) -> Option<Command> {
+        let command = match self {
+            Action::Prev => Command::PrevTrack,
+            Action::Next => Command::NextTrack,
+            Action::PlayPause => Command::TogglePlayPause,
+            Action::Play => Command::Play,
+            Action::Pause => Command::Pause,
+            Action::Shuffle => Command::ToggleShuffle,
+            Action::Repeat => Command::ToggleRepeatState,
+            Action::Volume { action: VolumeAction::Get } => Command::GetVolume,
+            Action::Volume { action: VolumeAction::Set { volume } } => Command::SetVolume(*volume),
+            Action::NowPlaying => Command::GetNowPlaying,
+            Action::Save => Command::SaveTrack,
+            Action::Unsave => Command::RemoveSavedTrack,
+            Action::PlayPlaylist { uri } => Command::PlayPlaylist(uri.clone()),
+            Action::Shutdown => Command::Shutdown,
+            Action::Reboot => Command::Reboot,
+            Action::Shell | Action::Watch | Action::Bridge { .. } | Action::Mqtt { .. } => return None,
+        };
+        Some(command)
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    // Single commands print their own errors.
+    init_logging(cli.verbose, cli.action.command().is_none());
+
+    let url = match parse_address(&cli.host) {
+        Ok(url) => url,
+        Err(e) => {
+            eprintln!("Invalid host {:?}: {}", cli.host, e);
+            exit(EXIT_USAGE);
+        }
+    };
+
+    match &cli.action {
+        Action::Watch => {
+            watch::run_watch(url, Duration::from_secs(cli.timeout)).await;
+            exit(0);
+        }
+        Action::Bridge { bind, token, allow_power } => {
+            if let Err(e) = bridge::run_bridge(url, Duration::from_secs(cli.timeout), *bind, token.clone(), *allow_power).await {
+                eprintln!("{}", e);
+                exit(EXIT_FAILED);
+            }
+            exit(0);
+        }
+        Action::Mqtt { broker, allow_power, username, password, topic, discovery_prefix, node_id } => {
+            let (host, port) = match parse_host_port(broker, 1883) {
+                Ok(host_port) => host_port,
+                Err(e) => {
+                    eprintln!("Invalid broker {:?}: {}", broker, e);
+                    exit(EXIT_USAGE);
+                }
+            };
+            let config = mqtt::MqttConfig {
+                host,
+                port,
+                credentials: username.clone().zip(password.clone()),
+                topic: topic.clone(),
+                discovery_prefix: discovery_prefix.clone(),
+                node_id: node_id.clone(),
+                allow_power: *allow_power,
+            };
+            mqtt::run_mqtt(url, Duration::from_secs(cli.timeout), config).await;
+            exit(0);
+        }
+        _ => {}
+    }
+
+    let session = match WsSession::connect(url, Duration::from_secs(cli.timeout)).await {
+        Ok(session) => session,
+        Err(e) => {
+            let result = match e {
+                ConnectError::Timeout => CommandResult::Timeout,
+                _ => CommandResult::Disconnected,
+            };
+            print_error(&cli, "connect", &e.to_string(), result);
+            exit(exit_code(result));
+        }
+    };
+
+    let command = match cli.action.command() {
+        Some(command) => command,
+        None => {
+            if let Err(e) = shell::run_shell(session).await {
+                eprintln!("{}", e);
+                exit(EXIT_FAILED);
+            }
+            exit(0);
+        }
+    };
+
+    let reply = session.request(&command).await;
+    session.close().await;
+    exit(print_reply(&cli, &command, reply));
+}
+
+/// Logs to stderr, so the output on stdout stays parsable. Without
+/// `verbose` only errors are logged, plus warnings of this crate when
+/// `warnings` is set; other crates log warnings only with `verbose`.
+fn init_logging(verbose: bool, warnings: bool) {
+    let level = match (verbose, warnings) {
+        (true, _) => Level::DEBUG,
+        (false, true) => Level::WARN,
+        (false, false) => Level::ERROR,
+    };
+    let filter = Targets::new()
+        .with_default(if verbose { Level::WARN } else { Level::ERROR })
+        .with_target("spotifypi_control_panel_fl", level)
+        .with_target("spotifypi_ctl", level);
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(filter)
+        .init();
+}
+
+fn print_reply(cli: &Cli, command: &Command, reply: Result<Option<WSEventValue>, CommandResult>) -> i32 {
+    let event = match reply {
+        Ok(event) => event,
+        Err(result) => {
+            print_error(cli, command.name(), result.as_str(), result);
+            return exit_code(result);
+        }
+    };
+    if let Some(false) = event.as_ref().and_then(|e| command.reply_result(e)) {
+        print_error(cli, command.name(), "failed", CommandResult::Failed);
+        return EXIT_FAILED;
+    }
+
+    let mut output = json!({ "command": command.name(), "result": "ok" });
+    let text = match &event {
+        Some(WSEventValue::Volume(volume)) => {
+            output["volume"] = json!(volume);
+            volume.to_string()
+        }
+        Some(WSEventValue::NowPlaying(Some(track))) => {
+            output["track"] = json!({
+                "id": track.id,
+                "title": track.title,
+                "artist": track.artist,
+                "album": track.album,
+            });
+            format!("{} - {} ({})", track.title, track.artist, track.album)
+        }
+        Some(WSEventValue::NowPlaying(None)) => {
+            output["track"] = json!(null);
+            "Nothing playing".to_string()
+        }
+        _ => "ok".to_string(),
+    };
+
+    if cli.json {
+        println!("{}", output);
+    } else {
+        println!("{}", text);
+    }
+    0
+}
+
+fn print_error(cli: &Cli, command_name: &str, message: &str, result: CommandResult) {
+    if cli.json {
+        println!("{}", json!({ "command": command_name, "result": result.as_str(), "error": message }));
+    } else {
+        eprintln!("{}: {}", command_name, message);
+    }
+}
+
+fn exit_code(result: CommandResult) -> i32 {
+    match result {
+        CommandResult::Ok => 0,
+        CommandResult::Failed => EXIT_FAILED,
+        CommandResult::Missing => EXIT_MISSING,
+        CommandResult::NotFound => EXIT_NOT_FOUND,
+        CommandResult::Timeout => EXIT_TIMEOUT,
+        CommandResult::Disconnected => EXIT_CONNECT,
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::Arc;
+
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{sleep, Duration};
+
+use spotifypi_control_panel_fl::commands::Command;
+use spotifypi_control_panel_fl::player_state::PlayerState;
+
+use crate::supervisor::Supervisor;
+
+
+#[derive(Clone)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub credentials: Option<(String, String)>,
+    /// Prefix of the state, availability, command and result topics.
+    pub topic: String,
+    pub discovery_prefix: String,
+    pub node_id: String,
+    /// Whether `shutdown` and `reboot` are accepted on the command topic.
+    pub allow_power: bool,
+}
+
+/// Commands accepted on the command topic: playback, volume and the saved
+/// track, plus power commands with `allow_power`. Anyone who can publish on
+/// the broker can send them.
+fn is_allowed(command: &Command, allow_power: bool) -> bool {
+    match command {
+        Command::PrevTrack
+        | Command::NextTrack
+        | Command::TogglePlayPause
+        | Command::ToggleShuffle
+        | Command::ToggleRepeatState
+        | Command::Play
+        | Command::Pause
+        | Command::PlayPlaylist(_)
+        | Command::GetVolume
+        | Command::SetVolume(_)
+        | Command::GetNowPlaying
+        | Command::GetTrackSaved
+        | Command::SaveTrack
+        | Command::RemoveSavedTrack => true,
+        Command::Shutdown | Command::Reboot => allow_power,
+        Command::SetSchedule(_) => false,
+    }
+}
+
+/// Home Assistant entities: (component, object id, name, extra config).
+fn discovery_entities(topic: &str) -> Vec<(&'static str, &'static str, &'static str, Value)> {
+    let command_topic = format!("{}/command", topic);
+    let state_topic = format!("{}/state", topic);
+    let button = |command: &str, icon: &str| json!({
+        "command_topic": command_topic,
+        "payload_press": command,
+        "icon": icon,
+    });
+    let sensor = |field: &str, icon: &str| json!({
+        "state_topic": state_topic,
+        "value_template": format!("{{{{ value_json.now_playing.{} if value_json.now_playing else '' }}}}", field),
+        "icon": icon,
+    });
+    vec![
+        ("button", "prev_track", "Previous track", button("prev_track", "mdi:skip-previous")),
+        ("button", "play_pause", "Play / pause", button("toggle_play_pause", "mdi:play-pause")),
+        ("button", "next_track", "Next track", button("next_track", "mdi:skip-next")),
+        ("button", "shuffle", "Toggle shuffle", button("toggle_shuffle", "mdi:shuffle")),
+        ("button", "repeat", "Toggle repeat", button("toggle_repeat_state", "mdi:repeat")),
+        ("number", "volume", "Volume", json!({
+            "command_topic": command_topic,
+            "command_template": "set_volume {{ value | int }}",
+            "state_topic": state_topic,
+            "value_template": "{{ value_json.volume if value_json.volume is not none else 0 }}",
+            "min": 0,
+            "max": 100,
+            "icon": "mdi:volume-high",
+        })),
+        ("switch", "saved", "Saved to library", json!({
+            "command_topic": command_topic,
+            "payload_on": "save_track",
+            "payload_off": "remove_saved_track",
+            "state_topic": state_topic,
+            "value_template": "{{ 'save_track' if value_json.track_saved else 'remove_saved_track' }}",
+            "icon": "mdi:heart",
+        })),
+        ("sensor", "title", "Title", sensor("title", "mdi:music")),
+        ("sensor", "artist", "Artist", sensor("artist", "mdi:account-music")),
+        ("sensor", "album", "Album", sensor("album", "mdi:album")),
+    ]
+}
+
+/// Mirrors the player state to an MQTT broker until the process is stopped.
+///
+/// The state is published retained as JSON on `<topic>/state`, and
+/// `<topic>/availability` is `online` while the Pi is connected. Playback
+/// and volume commands such as `next_track` or `set_volume 30` published to
+/// `<topic>/command` are sent to the Pi, with their result on
+/// `<topic>/result`; see `is_allowed`. Home Assistant discovery configs describe the Pi as a
+/// device with playback buttons, a volume number and track sensors.
+pub async fn run_mqtt(url: url::Url, connect_timeout: Duration, config: MqttConfig) {
+    let availability_topic = format!("{}/availability", config.topic);
+    let command_topic = format!("{}/command", config.topic);
+
+    let mut options = MqttOptions::new(format!("spotifypi-ctl-{}", config.node_id), &config.host, config.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(&availability_topic, "offline", QoS::AtLeastOnce, true));
+    if let Some((username, password)) = &config.credentials {
+        options.set_credentials(username, password);
+    }
+    let (client, mut eventloop) = AsyncClient::new(options, 32);
+
+    let supervisor = Arc::new(Supervisor::new());
+    tokio::spawn(supervisor.clone().run(url, connect_timeout));
+    tokio::spawn(publish_state(client.clone(), config.topic.clone(), supervisor.clone()));
+
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                eprintln!("Connected to MQTT broker {}:{}", config.host, config.port);
+                // On its own task: the client waits for room in its request
+                // channel, which only this loop drains.
+                let client = client.clone();
+                let command_topic = command_topic.clone();
+                let config = config.clone();
+                let supervisor = supervisor.clone();
+                tokio::spawn(async move {
+                    let _ = client.subscribe(&command_topic, QoS::AtLeastOnce).await;
+                    publish_discovery(&client, &config).await;
+                    publish_player_state(&client, &config.topic, &supervisor.state()).await;
+                });
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) if publish.topic == command_topic => {
+                let payload = String::from_utf8_lossy(&publish.payload).trim().to_string();
+                let client = client.clone();
+                let topic = config.topic.clone();
+                let supervisor = supervisor.clone();
+                let allow_power = config.allow_power;
+                tokio::spawn(async move {
+                    let result = match Command::parse(&payload) {
+                        Ok(command) if !is_allowed(&command, allow_power) => json!({
+                            "command": command.name(),
+                            "result": "forbidden",
+                        }),
+                        Ok(command) => json!({
+                            "command": command.name(),
+                            "result": supervisor.send_command(&command).await.as_str(),
+                        }),
+                        Err(e) => json!({ "command": payload, "result": "invalid", "error": e }),
+                    };
+                    let _ = client.publish(format!("{}/result", topic), QoS::AtLeastOnce, false, result.to_string()).await;
+                });
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("MQTT connection error: {}", e);
+                sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+}
+
+async fn publish_discovery(client: &AsyncClient, config: &MqttConfig) {
+    let device = json!({
+        "identifiers": [config.node_id],
+        "name": "SpotifyPi",
+        "manufacturer": "SpotifyPi",
+        "model": "spotifypi-ctl mqtt",
+    });
+    for (component, object_id, name, mut entity) in discovery_entities(&config.topic) {
+        entity["name"] = json!(name);
+        entity["unique_id"] = json!(format!("{}_{}", config.node_id, object_id));
+        entity["availability_topic"] = json!(format!("{}/availability", config.topic));
+        entity["device"] = device.clone();
+        let topic = format!("{}/{}/{}/{}/config", config.discovery_prefix, component, config.node_id, object_id);
+        let _ = client.publish(topic, QoS::AtLeastOnce, true, entity.to_string()).await;
+    }
+}
+
+async fn publish_player_state(client: &AsyncClient, topic: &str, state: &PlayerState) {
+    let availability = if state.connected { "online" } else { "offline" };
+    let _ = client.publish(format!("{}/availability", topic), QoS::AtLeastOnce, true, availability).await;
+    let payload = serde_json::to_string(state).unwrap_or_default();
+    let _ = client.publish(format!("{}/state", topic), QoS::AtLeastOnce, true, payload).await;
+}
+
+async fn publish_state(client: AsyncClient, topic: String, supervisor: Arc<Supervisor>) {
+    let mut updates = supervisor.subscribe_state();
+    loop {
+        match updates.recv().await {
+            Ok(state) => publish_player_state(&client, &topic, &state).await,
+            Err(RecvError::Lagged(_)) => publish_player_state(&client, &topic, &supervisor.state()).await,
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
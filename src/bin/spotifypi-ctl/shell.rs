@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use futures::channel::mpsc::UnboundedSender;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, ExternalPrinter, Helper};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+use spotifypi_control_panel_fl::commands::Command;
+use spotifypi_control_panel_fl::my_enums::WSEventValue;
+use spotifypi_control_panel_fl::ws::WsSession;
+
+
+const META_COMMANDS: [&str; 4] = [":help", ":raw", ":frames", ":quit"];
+
+const HELP: &str = "Commands:
+    <command> [arg]   Send a command, e.g. `next_track` or `set_volume 20`
+    :raw <text>       Send <text> as-is without checking it
+    :frames           Toggle between decoded events and raw frames
+    :help             Show this help
+    :quit             Leave the shell (or Ctrl-D)";
+
+struct ShellHelper;
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let word = &line[..pos];
+        if word.contains(char::is_whitespace) {
+            return Ok((pos, Vec::new()));
+        }
+        let candidates = Command::NAMES.iter()
+            .chain(META_COMMANDS.iter())
+            .filter(|name| name.starts_with(word))
+            .map(|name| name.to_string())
+            .collect();
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// Interactive shell: reads commands with line editing while printing the
+/// events of `session` as they arrive.
+pub async fn run_shell(session: WsSession) -> Result<(), ReadlineError> {
+    let mut editor = Editor::<ShellHelper>::new()?;
+    editor.set_helper(Some(ShellHelper));
+    let history = history_path();
+    if let Some(path) = &history {
+        let _ = editor.load_history(path);
+    }
+
+    let show_frames = Arc::new(AtomicBool::new(false));
+    // The external printer keeps the prompt intact, but needs a terminal.
+    let mut print: Box<dyn FnMut(String) -> bool + Send> = match editor.create_external_printer() {
+        Ok(mut printer) => Box::new(move |line| printer.print(line).is_ok()),
+        Err(_) => Box::new(|line| {
+            println!("{}", line);
+            true
+        }),
+    };
+    let mut events = session.subscribe();
+    let mut frames = session.subscribe_frames();
+    let printer_task = tokio::spawn({
+        let show_frames = show_frames.clone();
+        async move {
+            loop {
+                let line = tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(WSEventValue::Disconnect) => "!! disconnected".to_string(),
+                        Ok(event) if !show_frames.load(Ordering::Relaxed) => format!("<< {:?}", event),
+                        Ok(_) => continue,
+                        Err(RecvError::Lagged(n)) => format!("!! {} events dropped", n),
+                        Err(RecvError::Closed) => break,
+                    },
+                    frame = frames.recv() => match frame {
+                        Ok(frame) if show_frames.load(Ordering::Relaxed) => format!("<< {}", frame),
+                        Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    },
+                };
+                if !print(line) {
+                    break;
+                }
+            }
+        }
+    });
+
+    let input_tx = session.input_sender();
+    let mut editor = tokio::task::spawn_blocking(move || {
+        read_loop(&mut editor, &input_tx, &show_frames);
+        editor
+    }).await.expect("shell thread panicked");
+
+    if let Some(path) = &history {
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let _ = editor.save_history(path);
+    }
+    printer_task.abort();
+    session.close().await;
+    Ok(())
+}
+
+fn read_loop(editor: &mut Editor<ShellHelper>, input_tx: &UnboundedSender<Message>, show_frames: &AtomicBool) {
+    println!("Type :help for help.");
+    loop {
+        let line = match editor.readline("spotifypi> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("{}", e);
+                break;
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line);
+
+        let text = match line.split_once(char::is_whitespace).map_or((line, ""), |(cmd, arg)| (cmd, arg.trim())) {
+            (":quit", _) => break,
+            (":help", _) => {
+                println!("{}", HELP);
+                continue;
+            }
+            (":frames", _) => {
+                let show = !show_frames.load(Ordering::Relaxed);
+                show_frames.store(show, Ordering::Relaxed);
+                println!("Showing {}", if show { "raw frames" } else { "decoded events" });
+                continue;
+            }
+            (":raw", text) => text.to_string(),
+            _ => match Command::parse(line) {
+                Ok(command) => command.to_string(),
+                Err(e) => {
+                    println!("{}", e);
+                    continue;
+                }
+            },
+        };
+        println!(">> {}", text);
+        if input_tx.unbounded_send(Message::text(text)).is_err() {
+            println!("!! not connected");
+        }
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("spotifypi-control-panel-fl").join("shell_history.txt"))
+}
@@ -0,0 +1,89 @@
+use std::sync::{Arc, Mutex, RwLock};
+
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::Duration;
+
+use spotifypi_control_panel_fl::commands::Command;
+use spotifypi_control_panel_fl::my_enums::{CommandResult, WSEventValue};
+use spotifypi_control_panel_fl::player_state::PlayerState;
+use spotifypi_control_panel_fl::ws::{keep_connected, WsSession};
+
+
+/// One persistent session shared by the long-running modes, together with
+/// the player state built from its events.
+pub struct Supervisor {
+    session: RwLock<Option<Arc<WsSession>>>,
+    state: Mutex<PlayerState>,
+    state_tx: broadcast::Sender<PlayerState>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        let (state_tx, _) = broadcast::channel(16);
+        Self {
+            session: RwLock::new(None),
+            state: Mutex::new(PlayerState::default()),
+            state_tx,
+        }
+    }
+
+    pub fn state(&self) -> PlayerState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Receives the whole state every time it changes.
+    pub fn subscribe_state(&self) -> broadcast::Receiver<PlayerState> {
+        self.state_tx.subscribe()
+    }
+
+    fn apply(&self, event: &WSEventValue) {
+        let mut state = self.state.lock().unwrap();
+        if state.apply(event) {
+            let _ = self.state_tx.send(state.clone());
+        }
+    }
+
+    /// Keeps the session open, reconnecting when it drops. Never returns.
+    pub async fn run(self: Arc<Self>, url: url::Url, connect_timeout: Duration) {
+        keep_connected(url, connect_timeout, |result| {
+            let supervisor = self.clone();
+            async move {
+                let session = match result {
+                    Ok(session) => Arc::new(session),
+                    Err(_) => return,
+                };
+                let mut events = session.subscribe();
+                *supervisor.session.write().unwrap() = Some(session.clone());
+                supervisor.apply(&WSEventValue::Connect);
+                session.send(&Command::GetVolume);
+                session.send(&Command::GetNowPlaying);
+                session.send(&Command::GetTrackSaved);
+
+                loop {
+                    match events.recv().await {
+                        Ok(WSEventValue::Disconnect) | Err(RecvError::Closed) => break,
+                        Ok(event) => supervisor.apply(&event),
+                        Err(RecvError::Lagged(_)) => {}
+                    }
+                }
+                *supervisor.session.write().unwrap() = None;
+                session.close().await;
+                supervisor.apply(&WSEventValue::Disconnect);
+            }
+        }).await;
+    }
+
+    /// Sends `command` over the current session and waits for its reply.
+    pub async fn send_command(&self, command: &Command) -> CommandResult {
+        let session = self.session.read().unwrap().clone();
+        match session {
+            Some(session) => match session.request(command).await {
+                Ok(Some(event)) if command.reply_result(&event) == Some(false) => CommandResult::Failed,
+                Ok(_) => CommandResult::Ok,
+                Err(result) => result,
+            },
+            None => CommandResult::Disconnected,
+        }
+    }
+}
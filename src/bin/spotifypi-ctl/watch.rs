@@ -0,0 +1,69 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use chrono::{DateTime, Local, SecondsFormat};
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::Duration;
+
+use spotifypi_control_panel_fl::commands::Command;
+use spotifypi_control_panel_fl::my_enums::WSEventValue;
+use spotifypi_control_panel_fl::ws::keep_connected;
+
+
+/// Prints every event as one JSON object per line, reconnecting forever.
+/// Each line has a `type` of `event`, `connected`, `disconnected` or `gap`;
+/// `connected` lines after an outage carry the time the outage began.
+pub async fn run_watch(url: url::Url, connect_timeout: Duration) {
+    let disconnected_at: Rc<Cell<Option<DateTime<Local>>>> = Rc::new(Cell::new(None));
+    keep_connected(url, connect_timeout, |result| {
+        let disconnected_at = disconnected_at.clone();
+        async move {
+            let gap_since = disconnected_at.take();
+            let session = match result {
+                Ok(session) => session,
+                Err(e) => {
+                    if gap_since.is_none() {
+                        print_line(json!({ "type": "disconnected", "reason": e.to_string() }));
+                    }
+                    disconnected_at.set(Some(gap_since.unwrap_or_else(Local::now)));
+                    return;
+                }
+            };
+            print_line(json!({
+                "type": "connected",
+                "gap_since": gap_since.map(format_time),
+            }));
+
+            let mut events = session.subscribe();
+            session.send(&Command::GetVolume);
+            session.send(&Command::GetNowPlaying);
+            loop {
+                match events.recv().await {
+                    Ok(WSEventValue::Disconnect) | Err(RecvError::Closed) => break,
+                    Ok(event) => {
+                        let mut line = serde_json::to_value(&event).unwrap_or(Value::Null);
+                        line["type"] = json!("event");
+                        print_line(line);
+                    }
+                    Err(RecvError::Lagged(dropped)) => {
+                        print_line(json!({ "type": "gap", "dropped_events": dropped }));
+                    }
+                }
+            }
+            session.close().await;
+
+            print_line(json!({ "type": "disconnected" }));
+            disconnected_at.set(Some(Local::now()));
+        }
+    }).await;
+}
+
+fn print_line(mut line: Value) {
+    line["time"] = json!(format_time(Local::now()));
+    println!("{}", line);
+}
+
+fn format_time(time: DateTime<Local>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, false)
+}
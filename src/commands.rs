@@ -0,0 +1,163 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::my_enums::WSEventValue;
+
+
+/// A command understood by the SpotifyPi server, sent as a text frame.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Command {
+    PrevTrack,
+    NextTrack,
+    TogglePlayPause,
+    ToggleShuffle,
+    ToggleRepeatState,
+    Play,
+    Pause,
+    PlayPlaylist(String),
+    GetVolume,
+    SetVolume(u32),
+    GetNowPlaying,
+    GetTrackSaved,
+    SaveTrack,
+    RemoveSavedTrack,
+    SetSchedule(String),
+    Shutdown,
+    Reboot,
+}
+
+impl Command {
+    pub const NAMES: [&'static str; 17] = [
+        "prev_track",
+        "next_track",
+        "toggle_play_pause",
+        "toggle_shuffle",
+        "toggle_repeat_state",
+        "play",
+        "pause",
+        "play_playlist",
+        "get_volume",
+        "set_volume",
+        "get_now_playing",
+        "get_track_saved",
+        "save_track",
+        "remove_saved_track",
+        "set_schedule",
+        "shutdown",
+        "reboot",
+    ];
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (name, arg) = match text.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (text, ""),
+        };
+        let command = match name {
+            "prev_track" => Command::PrevTrack,
+            "next_track" => Command::NextTrack,
+            "toggle_play_pause" => Command::TogglePlayPause,
+            "toggle_shuffle" => Command::ToggleShuffle,
+            "toggle_repeat_state" => Command::ToggleRepeatState,
+            "play" => Command::Play,
+            "pause" => Command::Pause,
+            "play_playlist" if !arg.is_empty() => Command::PlayPlaylist(arg.to_string()),
+            "get_volume" => Command::GetVolume,
+            "set_volume" => match arg.parse::<u32>() {
+                Ok(volume) if volume <= 100 => Command::SetVolume(volume),
+                _ => return Err(format!("Invalid volume: {:?}", arg)),
+            },
+            "get_now_playing" => Command::GetNowPlaying,
+            "get_track_saved" => Command::GetTrackSaved,
+            "save_track" => Command::SaveTrack,
+            "remove_saved_track" => Command::RemoveSavedTrack,
+            "set_schedule" if !arg.is_empty() => Command::SetSchedule(arg.to_string()),
+            "shutdown" => Command::Shutdown,
+            "reboot" => Command::Reboot,
+            "play_playlist" | "set_schedule" => return Err(format!("Missing argument for {}", name)),
+            _ => return Err(format!("Unknown command: {:?}", name)),
+        };
+        Ok(command)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::PrevTrack => "prev_track",
+            Command::NextTrack => "next_track",
+            Command::TogglePlayPause => "toggle_play_pause",
+            Command::ToggleShuffle => "toggle_shuffle",
+            Command::ToggleRepeatState => "toggle_repeat_state",
+            Command::Play => "play",
+            Command::Pause => "pause",
+            Command::PlayPlaylist(_) => "play_playlist",
+            Command::GetVolume => "get_volume",
+            Command::SetVolume(_) => "set_volume",
+            Command::GetNowPlaying => "get_now_playing",
+            Command::GetTrackSaved => "get_track_saved",
+            Command::SaveTrack => "save_track",
+            Command::RemoveSavedTrack => "remove_saved_track",
+            Command::SetSchedule(_) => "set_schedule",
+            Command::Shutdown => "shutdown",
+            Command::Reboot => "reboot",
+        }
+    }
+
+    /// Whether the server answers this command. Power commands take the
+    /// server down before it can reply.
+    pub fn expects_reply(&self) -> bool {
+        !matches!(self, Command::Shutdown | Command::Reboot)
+    }
+
+    /// Interprets `event` as the reply to this command: `Some(success)` if it
+    /// is the reply, `None` if it is unrelated.
+    pub fn reply_result(&self, event: &WSEventValue) -> Option<bool> {
+        match (self, event) {
+            (_, WSEventValue::Missing) | (_, WSEventValue::NotFound) => Some(false),
+            (Command::PrevTrack, WSEventValue::PrevTrack(ok))
+            | (Command::NextTrack, WSEventValue::NextTrack(ok))
+            | (Command::TogglePlayPause, WSEventValue::TogglePlayPause(ok))
+            | (Command::ToggleShuffle, WSEventValue::ToggleShuffle(ok))
+            | (Command::ToggleRepeatState, WSEventValue::ToggleRepeatState(ok))
+            | (Command::Play, WSEventValue::Play(ok))
+            | (Command::Pause, WSEventValue::Pause(ok))
+            | (Command::PlayPlaylist(_), WSEventValue::PlayPlaylist(ok))
+            | (Command::SaveTrack, WSEventValue::SaveTrack(ok))
+            | (Command::RemoveSavedTrack, WSEventValue::RemoveSavedTrack(ok))
+            | (Command::SetSchedule(_), WSEventValue::SetSchedule(ok)) => Some(*ok),
+            // Other clients' changes are pushed as volume events too.
+            (Command::SetVolume(volume), WSEventValue::Volume(got)) => (*volume as i32 == *got).then_some(true),
+            (Command::GetVolume, WSEventValue::Volume(_))
+            | (Command::GetNowPlaying, WSEventValue::NowPlaying(_))
+            | (Command::GetTrackSaved, WSEventValue::TrackSaved(_)) => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::PlayPlaylist(uri) => write!(f, "{} {}", self.name(), uri),
+            Command::SetVolume(volume) => write!(f, "{} {}", self.name(), volume),
+            Command::SetSchedule(json) => write!(f, "{} {}", self.name(), json),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+impl TryFrom<String> for Command {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        Command::parse(&text)
+    }
+}
+
+impl From<Command> for String {
+    fn from(command: Command) -> Self {
+        command.to_string()
+    }
+}
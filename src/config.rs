@@ -0,0 +1,62 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::warn;
+
+
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("spotifypi-control-panel-fl"))
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("spotifypi-control-panel-fl"))
+}
+
+/// Loads `file_name` from the config dir, falling back to the default value
+/// when the file is missing or cannot be parsed.
+pub fn load_toml<T: DeserializeOwned + Default>(file_name: &str) -> T {
+    let path = match config_dir() {
+        Some(dir) => dir.join(file_name),
+        None => return T::default(),
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(_) => return T::default(),
+    };
+    match toml::from_str(&text) {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("Failed to parse {}: {}", path.display(), e);
+            T::default()
+        }
+    }
+}
+
+pub fn save_toml<T: Serialize>(file_name: &str, value: &T) -> Result<(), String> {
+    let dir = config_dir().ok_or("No config directory")?;
+    let text = toml::to_string_pretty(value).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    // Written next to the file and renamed over it, so a crash or full disk
+    // cannot leave it half written.
+    let path = dir.join(file_name);
+    let tmp_path = dir.join(format!("{}.tmp", file_name));
+    write_private(&tmp_path, text.as_bytes()).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| e.to_string())
+}
+
+/// Writes a new file only the user can read, as the settings hold tokens.
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    // The mode only applies to new files.
+    let _ = std::fs::remove_file(path);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use fltk::app;
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::my_enums::MyAppMessage;
+
+
+const VOLUME_STEP: i32 = 5;
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    PrevTrack,
+    NextTrack,
+    PlayPause,
+    ToggleShuffle,
+    ToggleRepeat,
+    ToggleSaved,
+    VolumeUp,
+    VolumeDown,
+}
+
+impl HotkeyAction {
+    pub const ALL: [HotkeyAction; 8] = [
+        HotkeyAction::PrevTrack,
+        HotkeyAction::NextTrack,
+        HotkeyAction::PlayPause,
+        HotkeyAction::ToggleShuffle,
+        HotkeyAction::ToggleRepeat,
+        HotkeyAction::ToggleSaved,
+        HotkeyAction::VolumeUp,
+        HotkeyAction::VolumeDown,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HotkeyAction::PrevTrack => "Previous track",
+            HotkeyAction::NextTrack => "Next track",
+            HotkeyAction::PlayPause => "Play / pause",
+            HotkeyAction::ToggleShuffle => "Toggle shuffle",
+            HotkeyAction::ToggleRepeat => "Toggle repeat",
+            HotkeyAction::ToggleSaved => "Save / unsave",
+            HotkeyAction::VolumeUp => "Volume up",
+            HotkeyAction::VolumeDown => "Volume down",
+        }
+    }
+
+    /// The message the matching button sends.
+    pub fn message(&self) -> MyAppMessage {
+        match self {
+            HotkeyAction::PrevTrack => MyAppMessage::PrevTrack,
+            HotkeyAction::NextTrack => MyAppMessage::NextTrack,
+            HotkeyAction::PlayPause => MyAppMessage::PlayPause,
+            HotkeyAction::ToggleShuffle => MyAppMessage::ToggleShuffle,
+            HotkeyAction::ToggleRepeat => MyAppMessage::ToggleRepeat,
+            HotkeyAction::ToggleSaved => MyAppMessage::ToggleSaved,
+            HotkeyAction::VolumeUp => MyAppMessage::StepVolume(VOLUME_STEP),
+            HotkeyAction::VolumeDown => MyAppMessage::StepVolume(-VOLUME_STEP),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Hotkey {
+    pub action: HotkeyAction,
+    /// For example `Ctrl+Alt+Right`; empty when the action has no shortcut.
+    pub shortcut: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Hotkeys {
+    #[serde(default)]
+    pub hotkeys: Vec<Hotkey>,
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        let defaults = [
+            (HotkeyAction::PrevTrack, "Ctrl+Alt+Left"),
+            (HotkeyAction::NextTrack, "Ctrl+Alt+Right"),
+            (HotkeyAction::PlayPause, "Ctrl+Alt+Space"),
+            (HotkeyAction::ToggleShuffle, ""),
+            (HotkeyAction::ToggleRepeat, ""),
+            (HotkeyAction::ToggleSaved, ""),
+            (HotkeyAction::VolumeUp, "Ctrl+Alt+Up"),
+            (HotkeyAction::VolumeDown, "Ctrl+Alt+Down"),
+        ];
+        Self {
+            hotkeys: defaults.iter()
+                .map(|(action, shortcut)| Hotkey { action: *action, shortcut: shortcut.to_string() })
+                .collect(),
+        }
+    }
+}
+
+impl Hotkeys {
+    pub fn shortcut(&self, action: HotkeyAction) -> &str {
+        self.hotkeys.iter()
+            .find(|h| h.action == action)
+            .map(|h| h.shortcut.as_str())
+            .unwrap_or("")
+    }
+
+    /// Shortcuts that cannot be parsed, and shortcuts bound to more than
+    /// one action.
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        let mut seen: HashMap<u32, HotkeyAction> = HashMap::new();
+        for hotkey in self.hotkeys.iter().filter(|h| !h.shortcut.trim().is_empty()) {
+            match HotKey::from_str(hotkey.shortcut.trim()) {
+                Ok(key) => {
+                    if let Some(other) = seen.insert(key.id(), hotkey.action) {
+                        conflicts.push(format!("{} is used by both {} and {}", hotkey.shortcut, other.label(), hotkey.action.label()));
+                    }
+                }
+                Err(e) => conflicts.push(format!("{}: {}", hotkey.action.label(), e)),
+            }
+        }
+        conflicts
+    }
+}
+
+/// Owns the registered global shortcuts and forwards presses to the app.
+pub struct HotkeyRegistrar {
+    manager: Option<GlobalHotKeyManager>,
+    registered: Vec<HotKey>,
+    actions: Arc<Mutex<HashMap<u32, HotkeyAction>>>,
+}
+
+impl HotkeyRegistrar {
+    pub fn new(sender: app::Sender<MyAppMessage>) -> Self {
+        let manager = match GlobalHotKeyManager::new() {
+            Ok(manager) => Some(manager),
+            Err(e) => {
+                warn!("Global shortcuts are not available: {}", e);
+                None
+            }
+        };
+        let actions: Arc<Mutex<HashMap<u32, HotkeyAction>>> = Arc::new(Mutex::new(HashMap::new()));
+        GlobalHotKeyEvent::set_event_handler(Some({
+            let actions = actions.clone();
+            move |event: GlobalHotKeyEvent| {
+                if event.state != HotKeyState::Pressed {
+                    return;
+                }
+                if let Some(action) = actions.lock().unwrap().get(&event.id) {
+                    sender.send(action.message());
+                    app::awake();
+                }
+            }
+        }));
+        Self {
+            manager,
+            registered: Vec::new(),
+            actions,
+        }
+    }
+
+    /// Replaces the registered shortcuts with `hotkeys`, returning the ones
+    /// that could not be registered, e.g. because another application
+    /// already grabbed them.
+    pub fn apply(&mut self, hotkeys: &Hotkeys) -> Vec<String> {
+        let manager = match &self.manager {
+            Some(manager) => manager,
+            None => return vec!["Global shortcuts are not available on this desktop".to_string()],
+        };
+        let _ = manager.unregister_all(&self.registered);
+        self.registered.clear();
+        let mut actions = self.actions.lock().unwrap();
+        actions.clear();
+
+        let mut errors = Vec::new();
+        for hotkey in hotkeys.hotkeys.iter().filter(|h| !h.shortcut.trim().is_empty()) {
+            let key = match HotKey::from_str(hotkey.shortcut.trim()) {
+                Ok(key) => key,
+                Err(e) => {
+                    errors.push(format!("{}: {}", hotkey.action.label(), e));
+                    continue;
+                }
+            };
+            if actions.contains_key(&key.id()) {
+                errors.push(format!("{}: {} is already used", hotkey.action.label(), hotkey.shortcut));
+                continue;
+            }
+            match manager.register(key) {
+                Ok(()) => {
+                    actions.insert(key.id(), hotkey.action);
+                    self.registered.push(key);
+                }
+                Err(e) => errors.push(format!("{}: {} ({})", hotkey.action.label(), hotkey.shortcut, e)),
+            }
+        }
+        errors
+    }
+}
@@ -0,0 +1,9 @@
+//! Protocol code shared by the control panel and the `spotifypi-ctl` client.
+
+pub mod address;
+pub mod commands;
+pub mod config;
+pub mod my_enums;
+pub mod player_state;
+pub mod scenes;
+pub mod ws;
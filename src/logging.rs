@@ -0,0 +1,70 @@
+//! Log output of the panel: stderr and a daily log file in the data dir,
+//! where it can be found on Windows too.
+
+use std::path::PathBuf;
+
+use tracing::Level;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt;
+use tracing_subscriber::prelude::*;
+
+use spotifypi_control_panel_fl::config::data_dir;
+
+
+const LOG_FILE_PREFIX: &str = "spotifypi";
+/// Daily files kept, including today's.
+const KEPT_LOGS: usize = 4;
+/// Log targets of this crate. Other crates only get warnings and errors in.
+const OWN_TARGET: &str = "spotifypi_control_panel_fl";
+
+
+/// Keep this alive while logging: dropping it flushes the log file.
+pub struct LogGuard {
+    _file_guard: Option<WorkerGuard>,
+    /// Directory of the log files, if they could be opened.
+    pub dir: Option<PathBuf>,
+}
+
+/// Installs the logger. Debug messages are logged only when `verbose`.
+pub fn init(verbose: bool) -> LogGuard {
+    let level = if verbose { Level::DEBUG } else { Level::INFO };
+    let filter = Targets::new()
+        .with_default(Level::WARN)
+        .with_target(OWN_TARGET, level);
+
+    let appender = data_dir().and_then(|dir| {
+        let appender = RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_FILE_PREFIX)
+            .filename_suffix("log")
+            .max_log_files(KEPT_LOGS)
+            .build(&dir);
+        match appender {
+            Ok(appender) => Some((appender, dir)),
+            Err(e) => {
+                eprintln!("Failed to open log file in {}: {}", dir.display(), e);
+                None
+            }
+        }
+    });
+    let (file_layer, file_guard, dir) = match appender {
+        Some((appender, dir)) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = fmt::layer().with_writer(writer).with_ansi(false);
+            (Some(layer), Some(guard), Some(dir))
+        }
+        None => (None, None, None),
+    };
+
+    let installed = tracing_subscriber::registry()
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .with(filter)
+        .try_init();
+    if installed.is_err() {
+        eprintln!("A logger is already installed");
+    }
+    LogGuard { _file_guard: file_guard, dir }
+}
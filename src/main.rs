@@ -1,83 +1,248 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-pub mod ws;
-pub mod my_enums;
+pub mod args;
+pub mod logging;
 pub mod my_dialogs;
+pub mod album_art;
+pub mod ramp;
+pub mod sleep_timer;
+pub mod scheduler;
+pub mod scripting;
+pub mod script_console;
+pub mod hotkeys;
+#[cfg(feature = "tray")]
+pub mod tray;
+pub mod notifications;
+pub mod settings;
+pub mod status_bar;
+pub mod theme;
+pub mod traffic_console;
+pub mod volume_control;
+pub mod volume_debouncer;
+#[cfg(target_os = "linux")]
+pub mod mpris;
 
-use ws::connect_to_ws;
-use my_enums::{MyAppMessage, WSEventValue, PowerOption};
-use my_dialogs::{show_power_dialog, show_alert_dialog};
+use spotifypi_control_panel_fl::{address, commands, config, my_enums, scenes, ws};
+use address::parse_address;
+use ws::{connect_to_ws, run_scene, ws_request};
+use args::Args;
+use my_enums::{CommandResult, MyAppMessage, WSEventValue, PowerOption, TrackInfo, SleepAction};
+use my_dialogs::{show_power_dialog, show_sleep_dialog, show_schedule_dialog, show_hotkey_dialog, show_notification_dialog, show_profile_dialog, show_alert_dialog, show_text_dialog};
+use album_art::load_album_art;
+use sleep_timer::{SleepTimer, SleepTimerStep};
+use scheduler::{AlarmRun, Schedule, Scheduler};
+use commands::Command;
+use scenes::Scenes;
+use scripting::{start_scripting, ScriptInput};
+use script_console::ScriptConsole;
+use traffic_console::TrafficConsole;
+use status_bar::StatusBar;
+use volume_control::VolumeControl;
+use volume_debouncer::VolumeDebouncer;
+use hotkeys::HotkeyRegistrar;
+#[cfg(feature = "tray")]
+use tray::Tray;
+use notifications::Notifier;
+use settings::{Profile, Settings, WindowGeometry};
+use theme::Theme;
 
+use clap::Parser;
 use fltk::{prelude::*, *};
-use fltk_theme::{WidgetScheme, SchemeType};
 use tokio::task;
+use tracing::{debug, info, warn, Instrument};
+use tokio::sync::broadcast;
 use futures::channel::mpsc::{unbounded, UnboundedSender};
 use tokio_tungstenite::tungstenite::protocol::Message;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use std::sync::mpsc;
+
+
+/// Commands without a reply by then show as timed out in the status bar.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+/// Choices in the Settings menu, in seconds.
+const CONNECT_TIMEOUTS: [u64; 4] = [5, 10, 20, 30];
 
 
 #[allow(dead_code)]
 struct MyApp {
     app: app::App,
     main_win: window::Window,
-    input_address: input::Input,
+    frame_art: frame::Frame,
+    input_address: misc::InputChoice,
+    /// Set while the address does not parse; the input gets a red border.
+    address_invalid: Rc<Cell<bool>>,
     button_connect: button::Button,
+    button_scripts: button::Button,
+    menu_settings: menu::MenuButton,
     button_prev: button::Button,
     button_play_pause: button::Button,
     button_next: button::Button,
     button_shuffle: button::Button,
     button_repeat: button::Button,
+    button_saved: button::Button,
     button_power: button::Button,
-    spinner_volume: misc::Spinner,
+    button_sleep: button::Button,
+    button_alarms: button::Button,
+    frame_sleep: frame::Frame,
+    volume_control: VolumeControl,
+    volume_debouncer: VolumeDebouncer,
+    buttons_scene: Vec<button::Button>,
+    frame_scene: frame::Frame,
 
     app_msg_sender: app::Sender<MyAppMessage>,
     app_msg_receiver: app::Receiver<MyAppMessage>,
     
     ws_input_sender: RefCell<Option<UnboundedSender<Message>>>,
+    ws_events: broadcast::Sender<WSEventValue>,
+    now_playing: Option<TrackInfo>,
+    track_saved: bool,
+    art_image: Rc<RefCell<Option<image::SharedImage>>>,
+    sleep_timer: Option<SleepTimer>,
+    schedule: Schedule,
+    /// Alarms were edited while disconnected and are sent on connect. Not
+    /// sending them otherwise keeps alarms set on the Pi from elsewhere.
+    schedule_unsent: bool,
+    scheduler: Scheduler,
+    alarm_run: Option<AlarmRun>,
+    scenes: Scenes,
+    scene_task: Option<task::JoinHandle<()>>,
+    script_tx: mpsc::Sender<ScriptInput>,
+    script_console: ScriptConsole,
+    traffic_console: TrafficConsole,
+    status_bar: StatusBar,
+    /// Sent commands still waiting for their reply, oldest first.
+    pending_commands: VecDeque<(Command, Instant)>,
+    settings: Settings,
+    /// Started with `--mini`; the window geometry is not saved then.
+    mini: bool,
+    /// Profile of the current connection, if it was made with one.
+    active_profile: Option<Profile>,
+    hotkey_registrar: HotkeyRegistrar,
+    #[cfg(feature = "tray")]
+    tray: Option<Tray>,
+    disconnect_requested: bool,
+    /// Between `ClickConnect` and the `Connect` event.
+    connecting: bool,
+    ws_task: Option<task::JoinHandle<()>>,
+    /// Bumped when a connection is dropped from this side, so events still
+    /// coming from its task are ignored.
+    connection_id: Arc<AtomicU64>,
+    notifier: Notifier,
+    connection_lost: bool,
+    #[cfg(target_os = "linux")]
+    mpris_tx: tokio::sync::mpsc::UnboundedSender<WSEventValue>,
+    tick_count: u64,
 }
 
 impl MyApp {
-    pub fn new() -> Self {
+    pub fn new(args: Args) -> Self {
         let app = app::App::default();
-        let widget_scheme = WidgetScheme::new(SchemeType::Fluent);
-        widget_scheme.apply();
+        let settings = Settings::load();
+        args.theme.unwrap_or(settings.theme).apply();
+
+        let scenes = Scenes::load();
+        let (win_width, win_height, min_width) = if args.mini {
+            (560, 114, 480)
+        } else if scenes.scenes.is_empty() {
+            (820, 242, 780)
+        } else {
+            (820, 282, 780)
+        };
 
         let mut main_win = window::Window::default()
-            .with_size(550, 220)
+            .with_size(win_width, win_height)
             .with_label("SpotifyPi Control Panel")
             .center_screen();
 
         let (app_msg_sender, app_msg_receiver) = app::channel::<MyAppMessage>();
 
-        let mut main_panel = group::Flex::default_fill().column(); 
+        let mut main_column = group::Flex::default_fill().column();
+        let mut main_row = group::Flex::default().row();
+        let spacer_art = frame::Frame::default();
+        let art_image = Rc::new(RefCell::new(None));
+        let mut frame_art = Self::create_art_frame(art_image.clone());
+
+        let mut main_panel = group::Flex::default().column();
         let spacer_top = frame::Frame::default();
 
         let mut row1 = group::Flex::default().row();
-        let (mut input_address, mut button_connect) = Self::row1_panel(&mut row1);
-        input_address.set_value("spotifypi.local:9487");
-        button_connect.emit(app_msg_sender, MyAppMessage::ClickConnect);
+        let (mut input_address, mut button_connect, mut button_scripts, mut menu_settings) = Self::row1_panel(&mut row1);
+        Self::fill_address_choices(&mut input_address, &settings);
+        input_address.set_value(args.address.as_deref().unwrap_or(&settings.last_address));
+        input_address.emit(app_msg_sender.clone(), MyAppMessage::AddressChanged);
+        let address_invalid = Rc::new(Cell::new(false));
+        input_address.draw({
+            let address_invalid = address_invalid.clone();
+            move |w| {
+                if address_invalid.get() {
+                    draw::set_draw_color(enums::Color::Red);
+                    draw::draw_rect(w.x(), w.y(), w.w(), w.h());
+                    draw::draw_rect(w.x() + 1, w.y() + 1, w.w() - 2, w.h() - 2);
+                }
+            }
+        });
+        button_connect.emit(app_msg_sender.clone(), MyAppMessage::ClickConnect);
+        button_scripts.emit(app_msg_sender.clone(), MyAppMessage::ClickScripts);
+        menu_settings.add_emit("Protocol traffic...", enums::Shortcut::None, menu::MenuFlag::MenuDivider, app_msg_sender.clone(), MyAppMessage::ClickTraffic);
+        menu_settings.add_emit("Profiles...", enums::Shortcut::None, menu::MenuFlag::Normal, app_msg_sender.clone(), MyAppMessage::ClickProfiles);
+        menu_settings.add_emit("Shortcuts...", enums::Shortcut::None, menu::MenuFlag::Normal, app_msg_sender.clone(), MyAppMessage::ClickHotkeys);
+        menu_settings.add_emit("Notifications...", enums::Shortcut::None, menu::MenuFlag::Normal, app_msg_sender.clone(), MyAppMessage::ClickNotifications);
+        for (idx, theme) in Theme::ALL.iter().enumerate() {
+            let path = format!("Theme/{}", theme.name());
+            menu_settings.add_emit(&path, enums::Shortcut::None, menu::MenuFlag::Radio, app_msg_sender.clone(), MyAppMessage::SetTheme(idx));
+            if *theme == settings.theme {
+                if let Some(mut item) = menu_settings.find_item(&path) {
+                    item.set();
+                }
+            }
+        }
+        for secs in CONNECT_TIMEOUTS {
+            let path = format!("Connect timeout/{} seconds", secs);
+            menu_settings.add_emit(&path, enums::Shortcut::None, menu::MenuFlag::Radio, app_msg_sender.clone(), MyAppMessage::SetConnectTimeout(secs));
+            if secs == settings.connect_timeout {
+                if let Some(mut item) = menu_settings.find_item(&path) {
+                    item.set();
+                }
+            }
+        }
 
         let spacer1 = frame::Frame::default();
 
         let mut row2 = group::Flex::default().row();
         let (mut button_prev, mut button_play_pause, mut button_next) = Self::row2_panel(&mut row2);
-        button_prev.emit(app_msg_sender, MyAppMessage::PrevTrack);
-        button_play_pause.emit(app_msg_sender, MyAppMessage::PlayPause);
-        button_next.emit(app_msg_sender, MyAppMessage::NextTrack);
+        button_prev.emit(app_msg_sender.clone(), MyAppMessage::PrevTrack);
+        button_play_pause.emit(app_msg_sender.clone(), MyAppMessage::PlayPause);
+        button_next.emit(app_msg_sender.clone(), MyAppMessage::NextTrack);
 
         let spacer2 = frame::Frame::default();
     
         let mut row3 = group::Flex::default().row();
-        let (mut button_shuffle, mut button_repeat) = Self::row3_panel(&mut row3);
-        button_shuffle.emit(app_msg_sender, MyAppMessage::ToggleShuffle);
-        button_repeat.emit(app_msg_sender, MyAppMessage::ToggleRepeat);
+        let (mut button_shuffle, mut button_repeat, mut button_saved) = Self::row3_panel(&mut row3);
+        button_shuffle.emit(app_msg_sender.clone(), MyAppMessage::ToggleShuffle);
+        button_repeat.emit(app_msg_sender.clone(), MyAppMessage::ToggleRepeat);
+        button_saved.emit(app_msg_sender.clone(), MyAppMessage::ToggleSaved);
 
-        let _spacer3 = frame::Frame::default();
+        let spacer3 = frame::Frame::default();
         
         let mut row4 = group::Flex::default().row();
-        let (mut button_power, mut spinner_volume) = Self::row4_panel(&mut row4);
-        button_power.emit(app_msg_sender, MyAppMessage::ClickPower);
-        spinner_volume.emit(app_msg_sender, MyAppMessage::ChangeVolume);
+        let (mut button_power, mut button_sleep, mut button_alarms, frame_sleep, mut volume_control) = Self::row4_panel(&mut row4, app_msg_sender.clone());
+        button_power.emit(app_msg_sender.clone(), MyAppMessage::ClickPower);
+        button_sleep.emit(app_msg_sender.clone(), MyAppMessage::ClickSleep);
+        button_alarms.emit(app_msg_sender.clone(), MyAppMessage::ClickAlarms);
+        volume_control.set_value(settings.volume);
+
+        let spacer4 = frame::Frame::default();
+
+        let mut row5 = group::Flex::default().row();
+        let (mut buttons_scene, frame_scene) = Self::row5_panel(&mut row5, &scenes);
+        for (idx, button) in buttons_scene.iter_mut().enumerate() {
+            button.emit(app_msg_sender.clone(), MyAppMessage::RunScene(idx));
+        }
 
         let spacer_bottom = frame::Frame::default();
     
@@ -85,158 +250,620 @@ impl MyApp {
         main_panel.set_size(&row1, 32);
         main_panel.set_size(&spacer1, 8);
         main_panel.set_size(&row2, 32);
-        main_panel.set_size(&spacer2, 8);
-        main_panel.set_size(&row3, 32);
-        main_panel.set_size(&row4, 32);
+        if args.mini {
+            // Only the address and playback rows.
+            for spacer in [&spacer2, &spacer3, &spacer4] {
+                main_panel.set_size(spacer, 0);
+            }
+            for row in [&mut row3, &mut row4, &mut row5] {
+                main_panel.set_size(row, 0);
+                row.hide();
+            }
+        } else {
+            main_panel.set_size(&spacer2, 8);
+            main_panel.set_size(&row3, 32);
+            main_panel.set_size(&row4, 32);
+            if scenes.scenes.is_empty() {
+                main_panel.set_size(&spacer4, 0);
+                main_panel.set_size(&row5, 0);
+                row5.hide();
+            } else {
+                main_panel.set_size(&spacer4, 8);
+                main_panel.set_size(&row5, 32);
+            }
+        }
         main_panel.set_size(&spacer_bottom, 10);
         main_panel.end();
 
-        main_win.resizable(&main_panel);
-        main_win.set_color(enums::Color::White);
+        main_row.set_size(&spacer_art, 10);
+        if args.mini {
+            main_row.set_size(&frame_art, 0);
+            frame_art.hide();
+        } else {
+            main_row.set_size(&frame_art, 200);
+        }
+        main_row.end();
+
+        let status_bar = StatusBar::new();
+        main_column.set_size(&status_bar.row, 22);
+        main_column.end();
+
+        main_win.set_callback({
+            let app_msg_sender = app_msg_sender.clone();
+            move |_| {
+                if app::event() == enums::Event::Close {
+                    app_msg_sender.send(MyAppMessage::CloseWindow);
+                }
+            }
+        });
+        main_win.resizable(&main_column);
+        main_win.set_color(theme::window_color());
         main_win.end();
+        match settings.window {
+            Some(geometry) if !args.mini => {
+                main_win.resize(geometry.x, geometry.y, geometry.w.max(min_width), geometry.h.max(win_height - 5));
+            }
+            _ => {}
+        }
         main_win.show();
-        main_win.size_range(500, 215, 0, 0);
+        if args.mini {
+            main_win.size_range(min_width, win_height, 0, win_height);
+        } else {
+            main_win.size_range(min_width, win_height - 5, 0, 0);
+        }
+
+        let script_console = ScriptConsole::new(app_msg_sender.clone());
+        let script_tx = start_scripting(app_msg_sender.clone());
+        let mut hotkey_registrar = HotkeyRegistrar::new(app_msg_sender.clone());
+        for error in hotkey_registrar.apply(&settings.hotkeys) {
+            warn!("Global shortcut not registered: {}", error);
+        }
+        #[cfg(feature = "tray")]
+        let tray = Tray::new(app_msg_sender.clone());
+        #[cfg(target_os = "linux")]
+        let mpris_tx = mpris::start_mpris(app_msg_sender.clone());
 
         let ws_input_sender = RefCell::new(None);
+        let (ws_events, _) = broadcast::channel(64);
+
+        if args.connect {
+            app_msg_sender.send(MyAppMessage::ClickConnect);
+        }
+
+        app::add_timeout3(1.0, {
+            let app_msg_sender = app_msg_sender.clone();
+            move |handle| {
+                app_msg_sender.send(MyAppMessage::Tick);
+                app::repeat_timeout3(1.0, handle);
+            }
+        });
 
         Self {
             app,
             main_win,
+            frame_art,
             input_address,
+            address_invalid,
             button_connect,
+            button_scripts,
+            menu_settings,
             button_prev,
             button_play_pause,
             button_next,
             button_shuffle,
             button_repeat,
+            button_saved,
             button_power,
-            spinner_volume,
+            button_sleep,
+            button_alarms,
+            frame_sleep,
+            volume_control,
+            volume_debouncer: VolumeDebouncer::new(),
+            buttons_scene,
+            frame_scene,
             app_msg_sender,
             app_msg_receiver,
             ws_input_sender,
+            ws_events,
+            now_playing: None,
+            track_saved: false,
+            art_image,
+            sleep_timer: None,
+            schedule: Schedule::load(),
+            schedule_unsent: false,
+            scheduler: Scheduler::default(),
+            alarm_run: None,
+            scenes,
+            scene_task: None,
+            script_tx,
+            script_console,
+            traffic_console: TrafficConsole::new(),
+            status_bar,
+            pending_commands: VecDeque::new(),
+            hotkey_registrar,
+            #[cfg(feature = "tray")]
+            tray,
+            disconnect_requested: false,
+            connecting: false,
+            ws_task: None,
+            connection_id: Arc::new(AtomicU64::new(0)),
+            notifier: Notifier::new(settings.notifications.clone()),
+            settings,
+            mini: args.mini,
+            active_profile: None,
+            connection_lost: false,
+            #[cfg(target_os = "linux")]
+            mpris_tx,
+            tick_count: 0,
         }
     }
 
     pub fn run(&mut self) {
         self.control_widgets_enable(false);
-        while self.app.wait() {
+        // Not `app.wait()`: that stops once no window is shown, and the main
+        // window may be hidden in the tray.
+        loop {
+            let _ = app::wait_for(1.0);
             if let Some(msg) = self.app_msg_receiver.recv() {
                 match msg {
                     MyAppMessage::ClickConnect => {
-                        let address = self.input_address.value();
-                        let ws_addr = format!("ws://{}", address);
-                        let url = match url::Url::parse(ws_addr.as_str()) {
-                            Ok(url) => url,
+                        if self.ws_input_sender.borrow().is_some() {
+                            continue;
+                        }
+                        let (profile, url) = match self.check_address() {
+                            Some(parsed) => parsed,
+                            None => {
+                                let _ = self.input_address.take_focus();
+                                continue;
+                            }
+                        };
+                        let token = profile.as_ref().and_then(|p| p.token.as_deref());
+                        let request = match ws_request(&url, token) {
+                            Ok(request) => request,
                             Err(e) => {
-                                eprintln!("Url::parse failed: {}", e);
-                                show_alert_dialog(&format!("{}", e));
-                                return;
+                                show_alert_dialog(&e);
+                                continue;
                             }
                         };
-                        println!("Connecting to {}", ws_addr);
+                        info!("Connecting to {}", url);
+                        self.traffic_console.add_status(&format!("Connecting to {}", url), false);
+                        self.active_profile = profile;
                         
-                        self.connect_widgets_enable(false, "Connecting...");
+                        self.connecting = true;
+                        self.set_tray_connecting();
+                        self.connect_widgets_enable(false);
+                        let entry = self.input_address.value().unwrap_or_default();
+                        self.status_bar.set_device(entry.trim());
+                        self.status_bar.set_state("Connecting...");
 
                         let (input_tx, input_rx)  = unbounded::<Message>();
                         self.ws_input_sender.replace(Some(input_tx));
                         let output_tx = self.app_msg_sender.clone();
+                        let frames_tx = self.app_msg_sender.clone();
+                        let recording_frames = self.traffic_console.recording();
+                        let events_tx = self.ws_events.clone();
+                        let current_id = self.connection_id.clone();
+                        let id = current_id.load(Ordering::SeqCst);
 
-                        task::spawn(async move {
-                            connect_to_ws(url, input_rx, output_tx).await;
-                        });
+                        let connect_timeout = Duration::from_secs(self.settings.connect_timeout);
+                        let span = tracing::info_span!("connection", address = %url);
+                        self.ws_task = Some(task::spawn(async move {
+                            connect_to_ws(request, connect_timeout, input_rx, |event_value| {
+                                if current_id.load(Ordering::SeqCst) == id {
+                                    let _ = events_tx.send(event_value.clone());
+                                    output_tx.send(MyAppMessage::WSEventValue(event_value));
+                                }
+                            }, |direction, frame| {
+                                if recording_frames.load(Ordering::Relaxed) {
+                                    frames_tx.send(MyAppMessage::Frame(direction, frame.to_string()));
+                                }
+                            }).await;
+                        }.instrument(span)));
+                    }
+                    MyAppMessage::AddressChanged => {
+                        let _ = self.check_address();
+                    }
+                    MyAppMessage::ClickDisconnect => {
+                        if let Some(task) = self.scene_task.take() {
+                            task.abort();
+                        }
+                        let input_tx = match self.ws_input_sender.replace(None) {
+                            Some(input_tx) => input_tx,
+                            None => continue,
+                        };
+                        self.connection_id.fetch_add(1, Ordering::SeqCst);
+                        if self.connecting {
+                            info!("Connect cancelled");
+                            self.traffic_console.add_status("Connect cancelled", false);
+                            if let Some(task) = self.ws_task.take() {
+                                task.abort();
+                            }
+                        } else {
+                            info!("Disconnecting");
+                            // The task sends the Close frame, then ends as the
+                            // channel is closed when `input_tx` is dropped.
+                            let _ = input_tx.unbounded_send(Message::Close(None));
+                            self.ws_task = None;
+                        }
+                        // The task's own events are ignored from now on, so
+                        // the rest of the app learns about it here.
+                        self.disconnect_requested = true;
+                        self.app_msg_sender.send(MyAppMessage::WSEventValue(WSEventValue::Disconnect));
                     }
                     MyAppMessage::PrevTrack => {
-                        println!("prev");
-                        self.send_command_to_ws("prev_track");
+                        debug!("prev");
+                        self.send_command_to_ws(Command::PrevTrack);
                     }
                     MyAppMessage::PlayPause => {
-                        println!("play/pause");
-                        self.send_command_to_ws("toggle_play_pause");
+                        debug!("play/pause");
+                        self.send_command_to_ws(Command::TogglePlayPause);
                     }
                     MyAppMessage::NextTrack => {
-                        println!("next");
-                        self.send_command_to_ws("next_track");
+                        debug!("next");
+                        self.send_command_to_ws(Command::NextTrack);
                     }
                     MyAppMessage::ToggleShuffle => {
-                        println!("shuffle");
-                        self.send_command_to_ws("toggle_shuffle");
+                        debug!("shuffle");
+                        self.send_command_to_ws(Command::ToggleShuffle);
                     }
                     MyAppMessage::ToggleRepeat => {
-                        println!("repeat");
-                        self.send_command_to_ws("toggle_repeat_state");
+                        debug!("repeat");
+                        self.send_command_to_ws(Command::ToggleRepeatState);
+                    }
+                    MyAppMessage::ToggleSaved => {
+                        if self.track_saved {
+                            debug!("remove saved track");
+                            self.send_command_to_ws(Command::RemoveSavedTrack);
+                        } else {
+                            debug!("save track");
+                            self.send_command_to_ws(Command::SaveTrack);
+                        }
                     }
                     MyAppMessage::ClickPower => {
-                        let dialog = show_power_dialog();
+                        let options: Vec<PowerOption> = [PowerOption::Shutdown, PowerOption::Reboot].into_iter()
+                            .filter(|option| self.power_allowed(*option))
+                            .collect();
+                        let dialog = show_power_dialog(&options);
                         match dialog.value() {
                             PowerOption::Shutdown => {
-                                println!("PowerOption: Shutdown");
-                                self.send_command_to_ws("shutdown");
+                                info!("PowerOption: Shutdown");
+                                self.send_command_to_ws(Command::Shutdown);
                             }
                             PowerOption::Reboot => {
-                                println!("PowerOption: Reboot");
-                                self.send_command_to_ws("reboot");
+                                info!("PowerOption: Reboot");
+                                self.send_command_to_ws(Command::Reboot);
+                            }
+                            PowerOption::Unknown => debug!("PowerOption: Unknown"),
+                        }
+                    }
+                    MyAppMessage::ClickSleep => {
+                        if self.sleep_timer.is_some() {
+                            info!("sleep timer: cancel");
+                            self.cancel_sleep_timer();
+                        } else {
+                            let dialog = show_sleep_dialog(self.power_allowed(PowerOption::Shutdown));
+                            let volume = self.volume_control.value();
+                            let timer = SleepTimer::new(dialog.value(), dialog.action(), volume, self.now_playing.as_ref());
+                            if timer.is_some() {
+                                info!("sleep timer: start");
+                                self.sleep_timer = timer;
+                                self.button_sleep.set_label("Cancel sleep");
+                                self.update_sleep_label();
+                            }
+                        }
+                    }
+                    MyAppMessage::ClickAlarms => {
+                        let dialog = show_schedule_dialog(&self.schedule);
+                        if let Some(schedule) = dialog.value() {
+                            if let Err(e) = schedule.save() {
+                                warn!("Failed to save schedule: {}", e);
+                                show_alert_dialog("Failed to save alarms.");
                             }
-                            PowerOption::Unknown => println!("PowerOption: Unknown"),
+                            self.schedule = schedule;
+                            if self.is_connected() {
+                                self.send_command_to_ws(self.schedule.push_command());
+                            } else {
+                                self.schedule_unsent = true;
+                            }
+                        }
+                    }
+                    MyAppMessage::RunScene(idx) => {
+                        let scene = self.scenes.scenes[idx].clone();
+                        let input_tx = match self.ws_input_sender.borrow().as_ref() {
+                            Some(s) => s.clone(),
+                            None => continue,
+                        };
+                        if let Some(step) = scene.steps.iter().find(|step| !self.command_allowed(&step.command)) {
+                            warn!("scene {}: {} is not allowed for this profile", scene.name, step.command);
+                            self.frame_scene.set_label(&format!("{}: {} not allowed", scene.name, step.command.name()));
+                            continue;
+                        }
+                        info!("scene: {}", scene.name);
+                        if let Some(task) = self.scene_task.take() {
+                            task.abort();
+                        }
+                        self.frame_scene.set_label(&format!("{}: running", scene.name));
+                        let events = self.ws_events.subscribe();
+                        let output_tx = self.app_msg_sender.clone();
+                        self.scene_task = Some(task::spawn(async move {
+                            run_scene(scene, input_tx, events, |report| {
+                                output_tx.send(MyAppMessage::SceneStep(report));
+                            }).await;
+                        }));
+                    }
+                    MyAppMessage::SceneStep(report) => {
+                        let result = report.result.as_str();
+                        debug!("scene {}: step {}/{} {} {}", report.scene, report.step, report.total, report.command, result);
+                        self.frame_scene.set_label(&format!("{}: {}/{} {} {}", report.scene, report.step, report.total, report.command, result));
+                    }
+                    MyAppMessage::ClickTraffic => {
+                        self.traffic_console.show();
+                    }
+                    MyAppMessage::Frame(direction, text) => {
+                        self.traffic_console.add_frame(direction, text);
+                    }
+                    MyAppMessage::ClickScripts => {
+                        self.script_console.show();
+                    }
+                    MyAppMessage::ScriptReload => {
+                        self.script_console.append("Reloading scripts");
+                        let _ = self.script_tx.send(ScriptInput::Reload);
+                    }
+                    MyAppMessage::ScriptLog(text) => {
+                        info!("script: {}", text);
+                        self.script_console.append(&text);
+                    }
+                    MyAppMessage::ScriptError(text) => {
+                        warn!("script error: {}", text);
+                        self.script_console.append(&format!("ERROR {}", text));
+                        self.script_console.show();
+                    }
+                    MyAppMessage::ScriptCommand(command) => {
+                        debug!("script command: {}", command);
+                        if self.is_connected() {
+                            self.send_command_to_ws(command);
+                        } else {
+                            self.script_console.append(&format!("{}: not connected", command));
+                        }
+                    }
+                    MyAppMessage::SendCommand(command) => {
+                        debug!("command: {}", command);
+                        if self.is_connected() {
+                            self.send_command_to_ws(command);
+                        }
+                    }
+                    MyAppMessage::ClickProfiles => {
+                        let dialog = show_profile_dialog(&self.settings.profiles);
+                        if let Some(profiles) = dialog.value() {
+                            self.settings.profiles = profiles;
+                            self.save_settings();
+                            Self::fill_address_choices(&mut self.input_address, &self.settings);
+                        }
+                    }
+                    MyAppMessage::ClickHotkeys => {
+                        let dialog = show_hotkey_dialog(&self.settings.hotkeys);
+                        if let Some(hotkeys) = dialog.value() {
+                            let errors = self.hotkey_registrar.apply(&hotkeys);
+                            self.settings.hotkeys = hotkeys;
+                            self.save_settings();
+                            if let Some(error) = errors.first() {
+                                warn!("Global shortcut not registered: {}", error);
+                                show_alert_dialog(&format!("Not registered: {}", error));
+                            }
+                        }
+                    }
+                    MyAppMessage::ClickNotifications => {
+                        let dialog = show_notification_dialog(&self.notifier.settings);
+                        if let Some(settings) = dialog.value() {
+                            self.notifier.settings = settings.clone();
+                            self.settings.notifications = settings;
+                            self.save_settings();
+                        }
+                    }
+                    MyAppMessage::SetTheme(idx) => {
+                        self.settings.theme = Theme::ALL[idx];
+                        self.save_settings();
+                        show_alert_dialog("The theme changes after a restart.");
+                    }
+                    MyAppMessage::SetConnectTimeout(secs) => {
+                        self.settings.connect_timeout = secs;
+                        self.save_settings();
+                    }
+                    MyAppMessage::StepVolume(delta) => {
+                        if !self.is_connected() {
+                            continue;
+                        }
+                        let volume = (self.volume_control.value() as i32 + delta).clamp(0, 100) as u32;
+                        self.volume_control.set_value(volume);
+                        self.change_volume(volume);
+                    }
+                    MyAppMessage::ShowWindow => {
+                        self.main_win.show();
+                    }
+                    MyAppMessage::CloseWindow => {
+                        self.save_window_geometry();
+                        if self.has_tray() {
+                            self.main_win.hide();
+                        } else {
+                            break;
+                        }
+                    }
+                    MyAppMessage::Quit => {
+                        if self.main_win.shown() {
+                            self.save_window_geometry();
+                        }
+                        break;
+                    }
+                    MyAppMessage::Tick => {
+                        self.tick_count += 1;
+                        self.tick_sleep_timer();
+                        self.tick_scheduler();
+                        self.expire_pending_commands();
+                        if self.tick_count.is_multiple_of(60) {
+                            let _ = self.script_tx.send(ScriptInput::Tick);
                         }
                     }
                     MyAppMessage::ChangeVolume => {
-                        let volume = self.spinner_volume.value() as u32;
-                        println!("volume: {}", volume);
-                        self.send_command_to_ws(&format!("set_volume {}", volume));
+                        self.change_volume(self.volume_control.value());
+                    }
+                    MyAppMessage::FlushVolume => {
+                        if let Some(volume) = self.volume_debouncer.flush(Instant::now()) {
+                            debug!("volume: {}", volume);
+                            self.send_command_to_ws(Command::SetVolume(volume));
+                        }
+                        self.schedule_volume_flush();
                     }
                     MyAppMessage::WSEventValue(value) => {
+                        let _ = self.script_tx.send(ScriptInput::Event(value.clone()));
+                        self.match_reply(&value);
+                        #[cfg(target_os = "linux")]
+                        let _ = self.mpris_tx.send(value.clone());
                         match value {
-                            WSEventValue::Unknown => println!("WSEvent: Unknown."),
-                            WSEventValue::Missing => println!("WSEvent: Missing."),
-                            WSEventValue::NotFound => println!("WSEvent: NotFound."),
-                            WSEventValue::Connect(success) => {
-                                println!("WSEvent: Connect: {}.", success);
-                                if success {
-                                    self.control_widgets_enable(true);
-                                    self.send_command_to_ws("get_volume");
-                                } else {
-                                    self.control_widgets_enable(false);
-                                    show_alert_dialog("Connect failed.");
+                            WSEventValue::Unknown => warn!("WSEvent: Unknown."),
+                            WSEventValue::Missing => warn!("WSEvent: Missing."),
+                            WSEventValue::NotFound => warn!("WSEvent: NotFound."),
+                            WSEventValue::Connect => {
+                                info!("WSEvent: Connect");
+                                self.connecting = false;
+                                self.set_tray_connected(true);
+                                self.traffic_console.add_status("Connected", false);
+                                self.status_bar.set_state("Connected");
+                                if std::mem::take(&mut self.connection_lost) {
+                                    self.notifier.connection_restored();
+                                }
+                                self.control_widgets_enable(true);
+                                let entry = self.input_address.value().unwrap_or_default();
+                                self.settings.remember_address(entry.trim());
+                                self.save_settings();
+                                Self::fill_address_choices(&mut self.input_address, &self.settings);
+                                if let Some(volume) = self.active_profile.as_ref().and_then(|p| p.volume) {
+                                    self.set_volume(volume);
                                 }
+                                self.send_command_to_ws(Command::GetVolume);
+                                self.send_command_to_ws(Command::GetNowPlaying);
+                                if std::mem::take(&mut self.schedule_unsent) {
+                                    self.send_command_to_ws(self.schedule.push_command());
+                                }
+                            }
+                            WSEventValue::ConnectFailed(error) => {
+                                info!("WSEvent: ConnectFailed: {}", error);
+                                self.connecting = false;
+                                self.set_tray_connected(false);
+                                self.ws_input_sender.replace(None);
+                                self.control_widgets_enable(false);
+                                self.traffic_console.add_status(&format!("Connect failed: {}", error), true);
+                                self.status_bar.set_error_state(error.short_name());
+                                show_alert_dialog(&format!("Connect failed.\n{}", error));
                             }
                             WSEventValue::Disconnect => {
-                                println!("WSEvent: Disconnect");
+                                info!("WSEvent: Disconnect");
+                                self.traffic_console.add_status("Disconnected", !self.disconnect_requested);
+                                self.connecting = false;
+                                self.ws_task = None;
+                                let state = if self.disconnect_requested { "Disconnected" } else { "Connection lost" };
+                                self.status_bar.set_state(state);
+                                self.status_bar.set_latency(None);
+                                self.pending_commands.clear();
+                                self.ws_input_sender.replace(None);
+                                self.alarm_run = None;
+                                if let Some(task) = self.scene_task.take() {
+                                    task.abort();
+                                }
                                 self.control_widgets_enable(false);
-                                show_alert_dialog("WebSocket connection closed.");
+                                self.cancel_sleep_timer();
+                                self.set_now_playing(None);
+                                self.set_tray_connected(false);
+                                if !std::mem::take(&mut self.disconnect_requested) {
+                                    self.connection_lost = true;
+                                    if Notifier::is_supported() {
+                                        self.notifier.connection_lost();
+                                    } else {
+                                        show_alert_dialog("WebSocket connection closed.");
+                                    }
+                                }
                             }
-                            WSEventValue::PrevTrack(success) => println!("WSEvent: PrevTrack ({}).", success),
-                            WSEventValue::NextTrack(success) => println!("WSEvent: NextTrack ({}).", success),
-                            WSEventValue::TogglePlayPause(success) => println!("WSEvent: TogglePlayPause ({}).", success),
-                            WSEventValue::ToggleShuffle(success) => println!("WSEvent: ToggleShuffle ({}).", success),
-                            WSEventValue::ToggleRepeatState(success) => println!("WSEvent: ToggleRepeatState ({}).", success),
+                            WSEventValue::PrevTrack(success) => debug!("WSEvent: PrevTrack ({}).", success),
+                            WSEventValue::NextTrack(success) => debug!("WSEvent: NextTrack ({}).", success),
+                            WSEventValue::TogglePlayPause(success) => debug!("WSEvent: TogglePlayPause ({}).", success),
+                            WSEventValue::ToggleShuffle(success) => debug!("WSEvent: ToggleShuffle ({}).", success),
+                            WSEventValue::ToggleRepeatState(success) => debug!("WSEvent: ToggleRepeatState ({}).", success),
+                            WSEventValue::Pause(success) => debug!("WSEvent: Pause ({}).", success),
+                            WSEventValue::Play(success) => debug!("WSEvent: Play ({}).", success),
+                            WSEventValue::PlayPlaylist(success) => debug!("WSEvent: PlayPlaylist ({}).", success),
+                            WSEventValue::SetSchedule(success) => debug!("WSEvent: SetSchedule ({}).", success),
                             WSEventValue::Volume(volume) => {
-                                println!("WSEvent: Volume ({})", volume);
-                                let value = volume as f64; 
-                                self.spinner_volume.set_value(value);
+                                debug!("WSEvent: Volume ({})", volume);
+                                if !self.volume_debouncer.is_echo(volume as u32, Instant::now()) {
+                                    self.volume_control.set_value(volume as u32);
+                                }
+                                // Saved on exit; fades and ramps change it every second.
+                                self.settings.volume = volume as u32;
+                            }
+                            WSEventValue::SaveTrack(success) => {
+                                debug!("WSEvent: SaveTrack ({}).", success);
+                                if success {
+                                    self.set_track_saved(true);
+                                }
+                            }
+                            WSEventValue::RemoveSavedTrack(success) => {
+                                debug!("WSEvent: RemoveSavedTrack ({}).", success);
+                                if success {
+                                    self.set_track_saved(false);
+                                }
+                            }
+                            WSEventValue::TrackSaved(saved) => {
+                                debug!("WSEvent: TrackSaved ({}).", saved);
+                                self.set_track_saved(saved);
+                            }
+                            WSEventValue::NowPlaying(track) => {
+                                match &track {
+                                    Some(track) => debug!("WSEvent: NowPlaying ({} - {})", track.title, track.artist),
+                                    None => debug!("WSEvent: NowPlaying (none)"),
+                                }
+                                if let Some(timer) = self.sleep_timer.as_mut() {
+                                    timer.on_track_change(track.as_ref());
+                                }
+                                self.set_now_playing(track);
                             }
                         }
                     }
+                    MyAppMessage::AlbumArtLoaded(track_id, path) => {
+                        let is_current = self.now_playing.as_ref().is_some_and(|t| t.id == track_id);
+                        if is_current {
+                            if let Some(track) = self.now_playing.as_ref() {
+                                self.notifier.track_changed(track, path.as_deref());
+                            }
+                            let image = path.and_then(|p| image::SharedImage::load(p).ok());
+                            self.set_art_image(image);
+                        }
+                    }
                 }
             }
         }
+        self.save_settings();
     }
 
-    fn row1_panel(parent: &mut group::Flex) -> (input::Input, button::Button) {
+    fn row1_panel(parent: &mut group::Flex) -> (misc::InputChoice, button::Button, button::Button, menu::MenuButton) {
         let spacer_left = frame::Frame::default();
         let label = frame::Frame::default()
-            .with_label("ws://")
+            .with_label("Address")
             .with_align(enums::Align::Inside | enums::Align::Right);
-        let input_address = input::Input::default();
+        let input_address = misc::InputChoice::default();
         let button_connect = Self::create_button("Connect");
+        let button_scripts = Self::create_button("Scripts");
+        let mut menu_settings = menu::MenuButton::default().with_label("Settings");
+        menu_settings.set_color(theme::button_color());
         let spacer_right = frame::Frame::default();
 
-        parent.set_size(&label, 45);
+        parent.set_size(&label, 65);
         parent.set_size(&button_connect, 90);
+        parent.set_size(&button_scripts, 80);
+        parent.set_size(&menu_settings, 90);
         parent.set_size(&spacer_left, 10);
         parent.set_size(&spacer_right, 10);
         parent.end();
 
-        (input_address, button_connect)
+        (input_address, button_connect, button_scripts, menu_settings)
     }
 
     fn row2_panel(parent: &mut group::Flex) -> (button::Button, button::Button, button::Button) {
@@ -255,93 +882,490 @@ impl MyApp {
         (button_prev, button_play_pause, button_next)
     }
 
-    fn row3_panel(parent: &mut group::Flex) -> (button::Button, button::Button) {
+    fn row3_panel(parent: &mut group::Flex) -> (button::Button, button::Button, button::Button) {
         let spacer_left = frame::Frame::default();
         let button_shuffle = Self::create_button("Toggle Shuffle");
         let button_repeat = Self::create_button("Toggle Repeat off / Single song / Whole playlist");
+        let button_saved = Self::create_button("\u{2661} Save");
         let spacer_right = frame::Frame::default();
 
         parent.set_size(&button_shuffle, 130);
+        parent.set_size(&button_saved, 90);
         parent.set_size(&spacer_left, 10);
         parent.set_size(&spacer_right, 10);
         parent.end();
 
-        (button_shuffle, button_repeat)
+        (button_shuffle, button_repeat, button_saved)
     }
 
-    fn row4_panel(parent: &mut group::Flex) -> (button::Button, misc::Spinner) {
+    fn row4_panel(parent: &mut group::Flex, app_msg_sender: app::Sender<MyAppMessage>) -> (button::Button, button::Button, button::Button, frame::Frame, VolumeControl) {
         let spacer_left = frame::Frame::default();
         let button_power = Self::create_button("Power");
-        frame::Frame::default();
-        let spinner_volume = Self::create_spinner("Volume:  ", 1.0, 0.0, 100.0, 50.0);
+        let button_sleep = Self::create_button("Sleep");
+        let button_alarms = Self::create_button("Alarms");
+        let frame_sleep = frame::Frame::default()
+            .with_align(enums::Align::Inside | enums::Align::Left);
+        let volume_control = VolumeControl::new(app_msg_sender);
         let spacer_right = frame::Frame::default();
 
         parent.set_size(&button_power, 90);
-        parent.set_size(&spinner_volume, 80);
+        parent.set_size(&button_sleep, 105);
+        parent.set_size(&button_alarms, 80);
+        parent.set_size(&volume_control.row, 180);
+        parent.set_size(&spacer_left, 10);
+        parent.set_size(&spacer_right, 10);
+        parent.end();
+
+        (button_power, button_sleep, button_alarms, frame_sleep, volume_control)
+    }
+
+    fn row5_panel(parent: &mut group::Flex, scenes: &Scenes) -> (Vec<button::Button>, frame::Frame) {
+        let spacer_left = frame::Frame::default();
+        let mut buttons_scene = Vec::new();
+        for scene in &scenes.scenes {
+            let button = Self::create_button(&scene.name);
+            parent.set_size(&button, 110);
+            buttons_scene.push(button);
+        }
+        let frame_scene = frame::Frame::default()
+            .with_align(enums::Align::Inside | enums::Align::Left);
+        let spacer_right = frame::Frame::default();
+
         parent.set_size(&spacer_left, 10);
         parent.set_size(&spacer_right, 10);
         parent.end();
 
-        (button_power, spinner_volume)
+        (buttons_scene, frame_scene)
+    }
+
+    fn create_art_frame(art_image: Rc<RefCell<Option<image::SharedImage>>>) -> frame::Frame {
+        let mut frame = frame::Frame::default().with_label("No album art");
+        frame.set_frame(enums::FrameType::FlatBox);
+        let (color, label_color) = theme::placeholder_colors();
+        frame.set_color(color);
+        frame.set_label_color(label_color);
+        frame.draw(move |f| {
+            if let Some(img) = art_image.borrow_mut().as_mut() {
+                img.scale(f.w(), f.h(), true, true);
+                let x = f.x() + (f.w() - img.w()) / 2;
+                let y = f.y() + (f.h() - img.h()) / 2;
+                img.draw(x, y, img.w(), img.h());
+            }
+        });
+        frame
     }
 
     fn create_button(title: &str) -> button::Button {
         let mut btn = button::Button::default().with_label(title);
-        btn.set_color(enums::Color::from_rgb(225, 225, 225));
+        btn.set_color(theme::button_color());
         btn
     }
     
-    fn create_spinner(title: &str, step: f64, min: f64, max: f64, value: f64) -> misc::Spinner {
-        let mut spin = misc::Spinner::default().with_label(title);
-        spin.set_step(step);
-        spin.set_range(min, max);
-        spin.set_value(value);
-        spin.visible_focus(false);
-        spin
+    fn set_now_playing(&mut self, track: Option<TrackInfo>) {
+        let same_track = match (&self.now_playing, &track) {
+            (Some(old), Some(new)) => old.id == new.id,
+            _ => false,
+        };
+        if !same_track {
+            self.set_art_image(None);
+            self.set_track_saved(false);
+            if track.is_some() {
+                self.send_command_to_ws(Command::GetTrackSaved);
+            }
+            // With album art, the notification waits until the art is loaded.
+            if let Some(track) = track.as_ref().filter(|t| t.art.is_none()) {
+                self.notifier.track_changed(track, None);
+            }
+            if let Some(art) = track.as_ref().and_then(|t| t.art.clone()) {
+                let track_id = track.as_ref().map(|t| t.id.clone()).unwrap_or_default();
+                let output_tx = self.app_msg_sender.clone();
+                task::spawn(async move {
+                    let path = load_album_art(art).await;
+                    output_tx.send(MyAppMessage::AlbumArtLoaded(track_id, path));
+                });
+            }
+        }
+        match &track {
+            Some(track) => self.frame_art.set_tooltip(&format!("{}\n{}\n{}", track.title, track.artist, track.album)),
+            None => self.frame_art.set_tooltip(""),
+        }
+        self.now_playing = track;
+    }
+
+    fn set_art_image(&mut self, image: Option<image::SharedImage>) {
+        if image.is_some() {
+            self.frame_art.set_label("");
+        } else {
+            self.frame_art.set_label("No album art");
+        }
+        self.art_image.replace(image);
+        self.frame_art.redraw();
+    }
+
+    fn set_track_saved(&mut self, saved: bool) {
+        self.track_saved = saved;
+        if saved {
+            self.button_saved.set_label("\u{2665} Saved");
+            self.button_saved.set_label_color(enums::Color::from_rgb(30, 185, 85));
+        } else {
+            self.button_saved.set_label("\u{2661} Save");
+            self.button_saved.set_label_color(theme::text_color());
+        }
+        self.button_saved.redraw();
     }
 
-    fn send_command_to_ws(&mut self, command: &str) {
-        if let Some(s) = self.ws_input_sender.borrow().as_ref() {
-            s.unbounded_send(Message::text(command)).expect("Could not send through channel");
+    fn tick_sleep_timer(&mut self) {
+        let step = match self.sleep_timer.as_mut() {
+            Some(timer) => timer.tick(Instant::now()),
+            None => return,
+        };
+        match step {
+            SleepTimerStep::Wait => {}
+            SleepTimerStep::SetVolume(volume) => {
+                debug!("sleep timer: volume {}", volume);
+                self.set_volume(volume);
+            }
+            SleepTimerStep::Finish(action) => {
+                let start_volume = self.sleep_timer.take().map(|t| t.start_volume()).unwrap_or(50);
+                // The profile may have changed since the timer was started.
+                let action = match action {
+                    SleepAction::Shutdown if !self.command_allowed(&Command::Shutdown) => {
+                        warn!("sleep timer: shutdown not allowed, pausing instead");
+                        SleepAction::Pause
+                    }
+                    action => action,
+                };
+                match action {
+                    SleepAction::Pause => {
+                        info!("sleep timer: pause");
+                        self.send_command_to_ws(Command::Pause);
+                        self.set_volume(start_volume);
+                    }
+                    SleepAction::Shutdown => {
+                        info!("sleep timer: shutdown");
+                        self.send_command_to_ws(Command::Shutdown);
+                    }
+                }
+                self.button_sleep.set_label("Sleep");
+            }
+        }
+        self.update_sleep_label();
+    }
+
+    fn cancel_sleep_timer(&mut self) {
+        if let Some(timer) = self.sleep_timer.take() {
+            let volume = timer.start_volume();
+            if self.volume_control.value() != volume {
+                self.set_volume(volume);
+            }
+        }
+        self.button_sleep.set_label("Sleep");
+        self.update_sleep_label();
+    }
+
+    fn update_sleep_label(&mut self) {
+        match &self.sleep_timer {
+            Some(timer) => self.frame_sleep.set_label(&timer.label(Instant::now())),
+            None => self.frame_sleep.set_label(""),
+        }
+    }
+
+    fn tick_scheduler(&mut self) {
+        let can_start = self.is_connected();
+        let due = self.scheduler.due_alarms(&self.schedule, chrono::Local::now(), can_start);
+        for alarm in due {
+            info!("alarm {}: start", alarm.summary());
+            self.cancel_sleep_timer();
+            self.set_volume(0);
+            if alarm.playlist_uri.is_empty() {
+                self.send_command_to_ws(Command::Play);
+            } else {
+                self.send_command_to_ws(Command::PlayPlaylist(alarm.playlist_uri.clone()));
+            }
+            self.alarm_run = Some(AlarmRun::new(&alarm));
+        }
+
+        let now = Instant::now();
+        let volume = match self.alarm_run.as_mut() {
+            Some(run) => run.tick(now),
+            None => return,
+        };
+        if let Some(volume) = volume {
+            self.set_volume(volume);
+        }
+        if self.alarm_run.as_ref().is_some_and(|run| run.is_finished(now)) {
+            self.alarm_run = None;
+        }
+    }
+
+    /// Whether closing the window hides it in the tray.
+    #[cfg(feature = "tray")]
+    fn has_tray(&self) -> bool {
+        self.tray.is_some()
+    }
+
+    #[cfg(not(feature = "tray"))]
+    fn has_tray(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "tray")]
+    fn set_tray_connected(&mut self, connected: bool) {
+        if let Some(tray) = self.tray.as_mut() {
+            tray.set_connected(connected);
+        }
+    }
+
+    #[cfg(not(feature = "tray"))]
+    fn set_tray_connected(&mut self, _connected: bool) {}
+
+    #[cfg(feature = "tray")]
+    fn set_tray_connecting(&mut self) {
+        if let Some(tray) = self.tray.as_mut() {
+            tray.set_connecting();
+        }
+    }
+
+    #[cfg(not(feature = "tray"))]
+    fn set_tray_connecting(&mut self) {}
+
+    fn save_settings(&self) {
+        if let Err(e) = self.settings.save() {
+            warn!("Failed to save settings: {}", e);
+        }
+    }
+
+    fn save_window_geometry(&mut self) {
+        if self.mini {
+            return;
+        }
+        let geometry = WindowGeometry {
+            x: self.main_win.x(),
+            y: self.main_win.y(),
+            w: self.main_win.w(),
+            h: self.main_win.h(),
+        };
+        if self.settings.window != Some(geometry) {
+            self.settings.window = Some(geometry);
+            self.save_settings();
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.ws_input_sender.borrow().is_some()
+    }
+
+    /// Fills the address box's menu with the profiles, then the recently used
+    /// addresses.
+    fn fill_address_choices(input: &mut misc::InputChoice, settings: &Settings) {
+        // Menu labels treat "/" as a submenu separator and a backslash as an escape.
+        fn menu_label(text: &str) -> String {
+            text.replace('\\', "\\\\").replace('/', "\\/")
+        }
+        input.clear();
+        for (idx, profile) in settings.profiles.iter().enumerate() {
+            // A leading "_" puts a divider after the item.
+            let last = idx + 1 == settings.profiles.len() && !settings.recent_addresses.is_empty();
+            let divider = if last { "_" } else { "" };
+            input.add(&format!("{}{}", divider, menu_label(&profile.nickname)));
+        }
+        for address in &settings.recent_addresses {
+            input.add(&menu_label(address));
+        }
+    }
+
+    fn power_allowed(&self, option: PowerOption) -> bool {
+        self.active_profile.as_ref().is_none_or(|p| p.allows(option))
+    }
+
+    /// The one check for commands the active profile forbids. Everything
+    /// sent to the Pi, including scene steps, goes through it.
+    fn command_allowed(&self, command: &Command) -> bool {
+        match command {
+            Command::Shutdown => self.power_allowed(PowerOption::Shutdown),
+            Command::Reboot => self.power_allowed(PowerOption::Reboot),
+            _ => true,
+        }
+    }
+
+    fn send_command_to_ws(&mut self, command: Command) {
+        if !self.command_allowed(&command) {
+            warn!("{} is not allowed for this profile", command);
+            return;
+        }
+        let sent = match self.ws_input_sender.borrow().as_ref() {
+            Some(s) => s.unbounded_send(Message::text(command.to_string())),
+            None => return,
+        };
+        // The connection task has ended; its Disconnect event is on the way.
+        if let Err(e) = sent {
+            warn!("{}: connection closed: {}", command, e);
+            self.status_bar.set_last_command(command.name(), CommandResult::Disconnected);
+            return;
+        }
+        if command.expects_reply() {
+            self.pending_commands.push_back((command, Instant::now()));
+        }
+    }
+
+    /// Pairs `event` with the oldest command it answers and shows the
+    /// result and round trip in the status bar.
+    fn match_reply(&mut self, event: &WSEventValue) {
+        // Error replies do not say which command they answer, so they are
+        // only matched when there is no doubt.
+        let is_error_reply = matches!(event, WSEventValue::Missing | WSEventValue::NotFound);
+        if is_error_reply && self.pending_commands.len() != 1 {
+            return;
+        }
+        let found = self.pending_commands.iter()
+            .position(|(command, _)| command.reply_result(event).is_some());
+        let (command, sent_at) = match found.and_then(|idx| self.pending_commands.remove(idx)) {
+            Some(pending) => pending,
+            None => return,
+        };
+        let result = match event {
+            WSEventValue::Missing => CommandResult::Missing,
+            WSEventValue::NotFound => CommandResult::NotFound,
+            _ if command.reply_result(event) == Some(true) => CommandResult::Ok,
+            _ => CommandResult::Failed,
+        };
+        self.status_bar.set_latency(Some(sent_at.elapsed()));
+        self.status_bar.set_last_command(command.name(), result);
+    }
+
+    fn expire_pending_commands(&mut self) {
+        while let Some((command, sent_at)) = self.pending_commands.front() {
+            if sent_at.elapsed() < COMMAND_TIMEOUT {
+                break;
+            }
+            self.status_bar.set_last_command(command.name(), CommandResult::Timeout);
+            self.pending_commands.pop_front();
         }
     }
     
-    fn connect_widgets_enable(&mut self, enable: bool, button_text: &str) {
-        self.button_connect.set_label(button_text);
+    /// Volume changes from the slider and shortcuts go through the debouncer,
+    /// so dragging the slider does not send a command for every step.
+    fn change_volume(&mut self, volume: u32) {
+        if let Some(volume) = self.volume_debouncer.change(volume, Instant::now()) {
+            debug!("volume: {}", volume);
+            self.send_command_to_ws(Command::SetVolume(volume));
+        }
+        self.schedule_volume_flush();
+    }
+
+    /// Volume changes of the sleep timer, alarms and profiles are sent right
+    /// away, but recorded so their echoes do not move the slider back.
+    fn set_volume(&mut self, volume: u32) {
+        self.volume_control.set_value(volume);
+        self.volume_debouncer.set(volume, Instant::now());
+        self.send_command_to_ws(Command::SetVolume(volume));
+    }
+
+    fn schedule_volume_flush(&mut self) {
+        if let Some(delay) = self.volume_debouncer.schedule_flush(Instant::now()) {
+            let app_msg_sender = self.app_msg_sender.clone();
+            app::add_timeout3(delay.as_secs_f64(), move |_| {
+                app_msg_sender.send(MyAppMessage::FlushVolume);
+            });
+        }
+    }
+
+    /// Parses the address input, or the address of the profile named in it,
+    /// and marks the input when it is invalid.
+    fn check_address(&mut self) -> Option<(Option<Profile>, url::Url)> {
+        let entry = self.input_address.value().unwrap_or_default();
+        let profile = self.settings.profile(entry.trim()).cloned();
+        let address = profile.as_ref().map_or(entry.trim(), |p| p.address.as_str());
+        let parsed = parse_address(address);
+        let was_invalid = self.address_invalid.replace(parsed.is_err());
+        match &parsed {
+            Ok(_) => {
+                self.input_address.set_tooltip("");
+                if was_invalid {
+                    self.status_bar.set_state("Disconnected");
+                }
+            }
+            Err(e) => {
+                self.input_address.set_tooltip(e);
+                self.status_bar.set_error_state(e);
+            }
+        }
+        self.input_address.redraw();
+        parsed.ok().map(|url| (profile, url))
+    }
+
+    /// The address row is editable only while disconnected; otherwise the
+    /// connect button cancels or disconnects.
+    fn connect_widgets_enable(&mut self, enable: bool) {
         if enable {
             self.input_address.activate();
-            self.button_connect.activate();
+            self.button_connect.set_label("Connect");
+            self.button_connect.emit(self.app_msg_sender.clone(), MyAppMessage::ClickConnect);
         } else {
             self.input_address.deactivate();
-            self.button_connect.deactivate();
+            let label = if self.connecting { "Cancel" } else { "Disconnect" };
+            self.button_connect.set_label(label);
+            self.button_connect.emit(self.app_msg_sender.clone(), MyAppMessage::ClickDisconnect);
         }
     }
 
     fn control_widgets_enable(&mut self, enable: bool) {
         if enable {
-            self.connect_widgets_enable(false, "Connected");
+            self.connect_widgets_enable(false);
             self.button_prev.activate();
             self.button_play_pause.activate();
             self.button_next.activate();
             self.button_shuffle.activate();
             self.button_repeat.activate();
-            self.button_power.activate();
-            self.spinner_volume.activate();
+            self.button_saved.activate();
+            if self.power_allowed(PowerOption::Shutdown) || self.power_allowed(PowerOption::Reboot) {
+                self.button_power.activate();
+            } else {
+                self.button_power.deactivate();
+            }
+            self.button_sleep.activate();
+            for button in self.buttons_scene.iter_mut() {
+                button.activate();
+            }
+            self.volume_control.activate();
         } else {
-            self.connect_widgets_enable(true, "Connect");
+            self.connect_widgets_enable(true);
             self.button_prev.deactivate();
             self.button_play_pause.deactivate();
             self.button_next.deactivate();
             self.button_shuffle.deactivate();
             self.button_repeat.deactivate();
+            self.button_saved.deactivate();
             self.button_power.deactivate();
-            self.spinner_volume.deactivate();
+            self.button_sleep.deactivate();
+            for button in self.buttons_scene.iter_mut() {
+                button.deactivate();
+            }
+            self.volume_control.deactivate();
+            self.volume_debouncer.clear();
         }
     }
 }
 
 #[tokio::main]
 async fn main() {
-    let mut my_app = MyApp::new();
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            let _ = e.print();
+            // Release builds on Windows have no console to print to.
+            if cfg!(all(windows, not(debug_assertions))) {
+                let _app = app::App::default();
+                show_text_dialog("SpotifyPi Control Panel", &e.to_string());
+            }
+            std::process::exit(if e.use_stderr() { 2 } else { 0 });
+        }
+    };
+    let log = logging::init(args.verbose);
+    if let Some(dir) = &log.dir {
+        info!("Logging to {}", dir.display());
+    }
+    let mut my_app = MyApp::new(args);
     my_app.run();
+    drop(log);
 }
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use fltk::app;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tracing::warn;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{dbus_interface, Connection, ConnectionBuilder, InterfaceRef};
+
+use spotifypi_control_panel_fl::commands::Command;
+use spotifypi_control_panel_fl::my_enums::{AlbumArt, MyAppMessage, WSEventValue};
+use spotifypi_control_panel_fl::player_state::PlayerState;
+
+
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+/// Repeat cycles through these, in this order, on every toggle.
+const LOOP_STATUSES: [&str; 3] = ["None", "Track", "Playlist"];
+
+struct MediaPlayer2;
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2")]
+impl MediaPlayer2 {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[dbus_interface(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn identity(&self) -> &str {
+        "SpotifyPi"
+    }
+
+    #[dbus_interface(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        vec!["spotify".to_string()]
+    }
+
+    #[dbus_interface(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// The connected Pi as seen by MPRIS clients. Shuffle, repeat and
+/// play/pause only have toggle commands, so their state is tracked from the
+/// replies and may be off until the user toggles them once.
+struct Player {
+    output_tx: app::Sender<MyAppMessage>,
+    state: PlayerState,
+    paused: bool,
+    shuffle: bool,
+    loop_index: usize,
+}
+
+impl Player {
+    fn send(&self, msg: MyAppMessage) {
+        self.output_tx.send(msg);
+    }
+
+    /// Applies `event`, returning whether any property changed.
+    fn apply(&mut self, event: &WSEventValue) -> bool {
+        match event {
+            WSEventValue::TogglePlayPause(true) => self.paused = !self.paused,
+            WSEventValue::Pause(true) => self.paused = true,
+            WSEventValue::Play(true) | WSEventValue::PlayPlaylist(true) => self.paused = false,
+            WSEventValue::ToggleShuffle(true) => self.shuffle = !self.shuffle,
+            WSEventValue::ToggleRepeatState(true) => self.loop_index = (self.loop_index + 1) % LOOP_STATUSES.len(),
+            _ => return self.state.apply(event),
+        }
+        true
+    }
+}
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.send(MyAppMessage::NextTrack);
+    }
+
+    fn previous(&self) {
+        self.send(MyAppMessage::PrevTrack);
+    }
+
+    fn play_pause(&self) {
+        self.send(MyAppMessage::PlayPause);
+    }
+
+    fn play(&self) {
+        self.send(MyAppMessage::SendCommand(Command::Play));
+    }
+
+    fn pause(&self) {
+        self.send(MyAppMessage::SendCommand(Command::Pause));
+    }
+
+    fn stop(&self) {
+        self.send(MyAppMessage::SendCommand(Command::Pause));
+    }
+
+    fn seek(&self, _offset: i64) {}
+
+    fn set_position(&self, _track_id: ObjectPath<'_>, _position: i64) {}
+
+    fn open_uri(&self, uri: String) {
+        self.send(MyAppMessage::SendCommand(Command::PlayPlaylist(uri)));
+    }
+
+    #[dbus_interface(property)]
+    fn playback_status(&self) -> &str {
+        match (&self.state.now_playing, self.paused) {
+            (None, _) => "Stopped",
+            (Some(_), true) => "Paused",
+            (Some(_), false) => "Playing",
+        }
+    }
+
+    #[dbus_interface(property)]
+    fn loop_status(&self) -> &str {
+        LOOP_STATUSES[self.loop_index]
+    }
+
+    #[dbus_interface(property)]
+    fn set_loop_status(&mut self, value: String) {
+        if let Some(target) = LOOP_STATUSES.iter().position(|s| *s == value) {
+            let toggles = (target + LOOP_STATUSES.len() - self.loop_index) % LOOP_STATUSES.len();
+            for _ in 0..toggles {
+                self.send(MyAppMessage::ToggleRepeat);
+            }
+        }
+    }
+
+    #[dbus_interface(property)]
+    fn shuffle(&self) -> bool {
+        self.shuffle
+    }
+
+    #[dbus_interface(property)]
+    fn set_shuffle(&mut self, value: bool) {
+        if value != self.shuffle {
+            self.send(MyAppMessage::ToggleShuffle);
+        }
+    }
+
+    #[dbus_interface(property)]
+    fn volume(&self) -> f64 {
+        self.state.volume.unwrap_or(0) as f64 / 100.0
+    }
+
+    #[dbus_interface(property)]
+    fn set_volume(&mut self, value: f64) {
+        let volume = (value.clamp(0.0, 1.0) * 100.0).round() as u32;
+        self.send(MyAppMessage::SendCommand(Command::SetVolume(volume)));
+    }
+
+    #[dbus_interface(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut metadata = HashMap::new();
+        let track = match &self.state.now_playing {
+            Some(track) => track,
+            None => {
+                metadata.insert("mpris:trackid".to_string(), Value::from(ObjectPath::from_static_str_unchecked(NO_TRACK)).into());
+                return metadata;
+            }
+        };
+        let id: String = track.id.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if let Ok(path) = ObjectPath::try_from(format!("/org/spotifypi/track/t{}", id)) {
+            metadata.insert("mpris:trackid".to_string(), Value::from(path).into());
+        }
+        metadata.insert("xesam:title".to_string(), Value::from(track.title.clone()).into());
+        metadata.insert("xesam:artist".to_string(), Value::from(vec![track.artist.clone()]).into());
+        metadata.insert("xesam:album".to_string(), Value::from(track.album.clone()).into());
+        if let Some(duration_ms) = track.duration_ms {
+            metadata.insert("mpris:length".to_string(), Value::from(duration_ms as i64 * 1000).into());
+        }
+        if let Some(AlbumArt::Url(url)) = &track.art {
+            metadata.insert("mpris:artUrl".to_string(), Value::from(url.clone()).into());
+        }
+        metadata
+    }
+
+    #[dbus_interface(property)]
+    fn position(&self) -> i64 {
+        let progress_ms = self.state.now_playing.as_ref().and_then(|t| t.progress_ms);
+        progress_ms.unwrap_or(0) as i64 * 1000
+    }
+
+    #[dbus_interface(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[dbus_interface(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[dbus_interface(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[dbus_interface(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_seek(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+/// Starts the MPRIS task. The connected Pi is published on the session bus
+/// while a connection is open, so desktop media keys, shell media widgets and
+/// `playerctl` can control it. Every received event should be sent to the
+/// returned channel.
+pub fn start_mpris(output_tx: app::Sender<MyAppMessage>) -> UnboundedSender<WSEventValue> {
+    let (event_tx, mut event_rx) = unbounded_channel::<WSEventValue>();
+    tokio::spawn(async move {
+        let mut bus: Option<(Connection, InterfaceRef<Player>)> = None;
+        while let Some(event) = event_rx.recv().await {
+            match event {
+                WSEventValue::Connect => {
+                    match register(output_tx.clone()).await {
+                        Ok(registered) => bus = Some(registered),
+                        Err(e) => warn!("MPRIS: failed to register on the session bus: {}", e),
+                    }
+                }
+                WSEventValue::ConnectFailed(_) | WSEventValue::Disconnect => bus = None,
+                event => {
+                    if let Some((_, player)) = &bus {
+                        if let Err(e) = update(player, &event).await {
+                            warn!("MPRIS: failed to publish changes: {}", e);
+                        }
+                    }
+                }
+            }
+        }
+    });
+    event_tx
+}
+
+async fn register(output_tx: app::Sender<MyAppMessage>) -> zbus::Result<(Connection, InterfaceRef<Player>)> {
+    let player = Player {
+        output_tx,
+        state: PlayerState { connected: true, ..PlayerState::default() },
+        paused: false,
+        shuffle: false,
+        loop_index: 0,
+    };
+    let connection = ConnectionBuilder::session()?
+        .name(format!("org.mpris.MediaPlayer2.spotifypi.instance{}", std::process::id()))?
+        .serve_at(OBJECT_PATH, MediaPlayer2)?
+        .serve_at(OBJECT_PATH, player)?
+        .build()
+        .await?;
+    let player = connection.object_server().interface::<_, Player>(OBJECT_PATH).await?;
+    Ok((connection, player))
+}
+
+async fn update(player: &InterfaceRef<Player>, event: &WSEventValue) -> zbus::Result<()> {
+    let mut iface = player.get_mut().await;
+    if !iface.apply(event) {
+        return Ok(());
+    }
+    let ctxt = player.signal_context();
+    iface.playback_status_changed(ctxt).await?;
+    iface.loop_status_changed(ctxt).await?;
+    iface.shuffle_changed(ctxt).await?;
+    iface.volume_changed(ctxt).await?;
+    iface.metadata_changed(ctxt).await?;
+    Ok(())
+}
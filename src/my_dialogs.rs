@@ -1,25 +1,55 @@
 use fltk::{prelude::*, *};
-use std::{rc::Rc, cell::Cell};
+use std::{rc::Rc, cell::{Cell, RefCell}};
 
-use crate::my_enums::PowerOption;
+use crate::my_enums::{PowerOption, SleepOption, SleepAction};
+use crate::scheduler::{Alarm, AlarmHost, Schedule, WEEKDAY_NAMES};
+use crate::hotkeys::{Hotkey, HotkeyAction, Hotkeys};
+use crate::notifications::NotificationSettings;
+use crate::settings::Profile;
+use crate::theme;
 
 
-pub fn show_power_dialog() -> PowerDialog {
-    PowerDialog::new()
+pub fn show_power_dialog(options: &[PowerOption]) -> PowerDialog {
+    PowerDialog::new(options)
+}
+
+pub fn show_schedule_dialog(schedule: &Schedule) -> ScheduleDialog {
+    ScheduleDialog::new(schedule)
+}
+
+pub fn show_sleep_dialog(allow_shutdown: bool) -> SleepDialog {
+    SleepDialog::new(allow_shutdown)
+}
+
+pub fn show_hotkey_dialog(hotkeys: &Hotkeys) -> HotkeyDialog {
+    HotkeyDialog::new(hotkeys)
+}
+
+pub fn show_notification_dialog(settings: &NotificationSettings) -> NotificationDialog {
+    NotificationDialog::new(settings)
+}
+
+pub fn show_profile_dialog(profiles: &[Profile]) -> ProfileDialog {
+    ProfileDialog::new(profiles)
 }
 
 pub fn show_alert_dialog(text: &str) -> AlertDialog {
     AlertDialog::new(text)
 }
 
+pub fn show_text_dialog(title: &str, text: &str) -> TextDialog {
+    TextDialog::new(title, text)
+}
+
 
 pub struct PowerDialog {
     confirm: bool,
     choice: menu::Choice,
+    options: Vec<PowerOption>,
 }
 
 impl PowerDialog {
-    pub fn new() -> Self {
+    pub fn new(options: &[PowerOption]) -> Self {
         let mut win = window::Window::default()
             .with_size(300, 100)
             .with_label("Power")
@@ -33,8 +63,13 @@ impl PowerDialog {
         
         let mut choice = menu::Choice::default()
             .with_size(90, 30);
-        choice.add_choice("Shutdown");
-        choice.add_choice("Reboot");
+        for option in options {
+            match option {
+                PowerOption::Shutdown => choice.add_choice("Shutdown"),
+                PowerOption::Reboot => choice.add_choice("Reboot"),
+                PowerOption::Unknown => {}
+            }
+        }
 
         let mut hpack = group::Pack::default()
             .with_size(240, 30)
@@ -52,7 +87,7 @@ impl PowerDialog {
         vpack.end();
         win.end();
 
-        win.set_color(enums::Color::White);
+        win.set_color(theme::window_color());
         win.make_modal(true);
         win.show();
 
@@ -87,16 +122,15 @@ impl PowerDialog {
         Self {
             confirm,
             choice,
+            options: options.to_vec(),
         }
     }
 
     pub fn value(&self) -> PowerOption {
         if self.confirm {
-            match self.choice.value() {
-                0 => PowerOption::Shutdown,
-                1 => PowerOption::Reboot,
-                _ => PowerOption::Unknown,
-            }
+            usize::try_from(self.choice.value()).ok()
+                .and_then(|idx| self.options.get(idx).copied())
+                .unwrap_or(PowerOption::Unknown)
         } else {
             PowerOption::Unknown
         }
@@ -104,23 +138,868 @@ impl PowerDialog {
 }
 
 
+pub struct ScheduleDialog {
+    confirm: bool,
+    alarms: Rc<RefCell<Vec<Alarm>>>,
+}
+
+#[derive(Clone)]
+struct AlarmFields {
+    check_enabled: button::CheckButton,
+    spinner_hour: misc::Spinner,
+    spinner_minute: misc::Spinner,
+    check_weekdays: Vec<button::CheckButton>,
+    input_playlist: input::Input,
+    spinner_volume: misc::Spinner,
+    spinner_ramp: misc::Spinner,
+    choice_host: menu::Choice,
+}
+
+impl AlarmFields {
+    fn load(&mut self, alarm: &Alarm) {
+        self.check_enabled.set_checked(alarm.enabled);
+        self.spinner_hour.set_value(alarm.hour as f64);
+        self.spinner_minute.set_value(alarm.minute as f64);
+        for (check, on) in self.check_weekdays.iter().zip(alarm.weekdays.iter()) {
+            check.set_checked(*on);
+        }
+        self.input_playlist.set_value(&alarm.playlist_uri);
+        self.spinner_volume.set_value(alarm.volume as f64);
+        self.spinner_ramp.set_value(alarm.ramp_minutes as f64);
+        self.choice_host.set_value(match alarm.host {
+            AlarmHost::Panel => 0,
+            AlarmHost::Pi => 1,
+        });
+    }
+
+    fn store(&self, alarm: &mut Alarm) {
+        alarm.enabled = self.check_enabled.is_checked();
+        alarm.hour = self.spinner_hour.value() as u32;
+        alarm.minute = self.spinner_minute.value() as u32;
+        for (on, check) in alarm.weekdays.iter_mut().zip(self.check_weekdays.iter()) {
+            *on = check.is_checked();
+        }
+        alarm.playlist_uri = self.input_playlist.value();
+        alarm.volume = self.spinner_volume.value() as u32;
+        alarm.ramp_minutes = self.spinner_ramp.value() as u32;
+        alarm.host = match self.choice_host.value() {
+            1 => AlarmHost::Pi,
+            _ => AlarmHost::Panel,
+        };
+    }
+
+    fn set_active(&mut self, active: bool) {
+        let mut widgets: Vec<Box<dyn WidgetExt>> = vec![
+            Box::new(self.check_enabled.clone()),
+            Box::new(self.spinner_hour.clone()),
+            Box::new(self.spinner_minute.clone()),
+            Box::new(self.input_playlist.clone()),
+            Box::new(self.spinner_volume.clone()),
+            Box::new(self.spinner_ramp.clone()),
+            Box::new(self.choice_host.clone()),
+        ];
+        for check in &self.check_weekdays {
+            widgets.push(Box::new(check.clone()));
+        }
+        for widget in widgets.iter_mut() {
+            if active {
+                widget.activate();
+            } else {
+                widget.deactivate();
+            }
+        }
+    }
+}
+
+impl ScheduleDialog {
+    pub fn new(schedule: &Schedule) -> Self {
+        let mut win = window::Window::default()
+            .with_size(480, 330)
+            .with_label("Alarms")
+            .center_screen();
+
+        let mut browser = browser::HoldBrowser::new(10, 10, 170, 270, None);
+        let mut button_add = button::Button::new(10, 290, 80, 30, "Add");
+        let mut button_remove = button::Button::new(100, 290, 80, 30, "Remove");
+
+        let check_enabled = button::CheckButton::new(200, 10, 100, 25, "Enabled");
+        let mut spinner_hour = misc::Spinner::new(270, 40, 60, 25, "Time:");
+        spinner_hour.set_range(0.0, 23.0);
+        spinner_hour.set_step(1.0);
+        let mut spinner_minute = misc::Spinner::new(340, 40, 60, 25, ":");
+        spinner_minute.set_range(0.0, 59.0);
+        spinner_minute.set_step(1.0);
+        let check_weekdays: Vec<button::CheckButton> = WEEKDAY_NAMES.iter()
+            .enumerate()
+            .map(|(i, name)| button::CheckButton::new(200 + i as i32 * 39, 75, 39, 25, None).with_label(name))
+            .collect();
+        let input_playlist = input::Input::new(270, 110, 200, 25, "Playlist:");
+        let mut spinner_volume = misc::Spinner::new(270, 145, 60, 25, "Volume:");
+        spinner_volume.set_range(0.0, 100.0);
+        spinner_volume.set_step(1.0);
+        let mut spinner_ramp = misc::Spinner::new(410, 145, 60, 25, "Ramp min:");
+        spinner_ramp.set_range(0.0, 60.0);
+        spinner_ramp.set_step(1.0);
+        let mut choice_host = menu::Choice::new(270, 180, 200, 25, "Run on:");
+        choice_host.add_choice("This panel");
+        choice_host.add_choice("SpotifyPi");
+
+        let mut button_cancel = button::Button::new(280, 290, 90, 30, "Cancel");
+        let mut button_confirm = button::Button::new(380, 290, 90, 30, "Save");
+
+        win.end();
+        win.set_color(theme::window_color());
+        win.make_modal(true);
+        win.show();
+
+        let mut fields = AlarmFields {
+            check_enabled,
+            spinner_hour,
+            spinner_minute,
+            check_weekdays,
+            input_playlist,
+            spinner_volume,
+            spinner_ramp,
+            choice_host,
+        };
+
+        let alarms = Rc::new(RefCell::new(schedule.alarms.clone()));
+        let selected: Rc<Cell<Option<usize>>> = Rc::new(Cell::new(None));
+
+        let mut refresh = {
+            let alarms = alarms.clone();
+            let selected = selected.clone();
+            let mut fields = fields.clone();
+            let mut browser = browser.clone();
+            move || {
+                browser.clear();
+                for alarm in alarms.borrow().iter() {
+                    browser.add(&alarm.summary());
+                }
+                match selected.get() {
+                    Some(idx) => {
+                        browser.select(idx as i32 + 1);
+                        fields.load(&alarms.borrow()[idx]);
+                        fields.set_active(true);
+                    }
+                    None => fields.set_active(false),
+                }
+            }
+        };
+        let store_selected = {
+            let alarms = alarms.clone();
+            let selected = selected.clone();
+            let fields = fields.clone();
+            move || {
+                if let Some(idx) = selected.get() {
+                    fields.store(&mut alarms.borrow_mut()[idx]);
+                }
+            }
+        };
+
+        if !alarms.borrow().is_empty() {
+            selected.set(Some(0));
+        }
+        fields.set_active(false);
+        refresh();
+
+        browser.set_callback({
+            let selected = selected.clone();
+            let store_selected = store_selected.clone();
+            let mut refresh = refresh.clone();
+            move |b| {
+                store_selected();
+                let line = b.value();
+                selected.set(if line > 0 { Some(line as usize - 1) } else { None });
+                refresh();
+            }
+        });
+
+        button_add.set_callback({
+            let alarms = alarms.clone();
+            let selected = selected.clone();
+            let store_selected = store_selected.clone();
+            let mut refresh = refresh.clone();
+            move |_| {
+                store_selected();
+                alarms.borrow_mut().push(Alarm::default());
+                selected.set(Some(alarms.borrow().len() - 1));
+                refresh();
+            }
+        });
+
+        button_remove.set_callback({
+            let alarms = alarms.clone();
+            let selected = selected.clone();
+            let mut refresh = refresh.clone();
+            move |_| {
+                if let Some(idx) = selected.get() {
+                    alarms.borrow_mut().remove(idx);
+                    let len = alarms.borrow().len();
+                    selected.set(if len > 0 { Some(idx.min(len - 1)) } else { None });
+                    refresh();
+                }
+            }
+        });
+
+        let is_confirm = Rc::new(Cell::new(false));
+
+        button_cancel.set_callback({
+            let mut win = win.clone();
+            let is_confirm_copy1 = is_confirm.clone();
+            move |_| {
+                win.hide();
+                is_confirm_copy1.set(false);
+            }
+        });
+
+        button_confirm.set_callback({
+            let mut win = win.clone();
+            let is_confirm_copy2 = is_confirm.clone();
+            move |_| {
+                store_selected();
+                win.hide();
+                is_confirm_copy2.set(true);
+            }
+        });
+
+        while win.shown() {
+            app::wait();
+        }
+
+        let confirm = is_confirm.get();
+
+        Self {
+            confirm,
+            alarms,
+        }
+    }
+
+    pub fn value(&self) -> Option<Schedule> {
+        if self.confirm {
+            Some(Schedule { alarms: self.alarms.borrow().clone() })
+        } else {
+            None
+        }
+    }
+}
+
+
+pub struct SleepDialog {
+    confirm: bool,
+    choice_duration: menu::Choice,
+    spinner_minutes: misc::Spinner,
+    choice_action: menu::Choice,
+}
+
+impl SleepDialog {
+    /// Offers to shut down at the end only with `allow_shutdown`.
+    pub fn new(allow_shutdown: bool) -> Self {
+        let mut win = window::Window::default()
+            .with_size(300, 190)
+            .with_label("Sleep timer")
+            .center_screen();
+
+        let mut vpack = group::Pack::default()
+            .with_size(240, 180)
+            .center_of_parent()
+            .with_type(group::PackType::Vertical);
+        vpack.set_spacing(10);
+
+        let mut choice_duration = menu::Choice::default()
+            .with_size(0, 30);
+        choice_duration.add_choice("15 minutes");
+        choice_duration.add_choice("30 minutes");
+        choice_duration.add_choice("60 minutes");
+        choice_duration.add_choice("End of track");
+        choice_duration.add_choice("Custom");
+
+        let hpack_minutes = group::Pack::default()
+            .with_size(240, 30)
+            .with_type(group::PackType::Horizontal);
+        frame::Frame::default()
+            .with_size(150, 30)
+            .with_label("Custom minutes:")
+            .with_align(enums::Align::Inside | enums::Align::Left);
+        let mut spinner_minutes = misc::Spinner::default()
+            .with_size(90, 30);
+        spinner_minutes.set_step(1.0);
+        spinner_minutes.set_range(1.0, 600.0);
+        spinner_minutes.set_value(45.0);
+        hpack_minutes.end();
+
+        let mut choice_action = menu::Choice::default()
+            .with_size(0, 30);
+        choice_action.add_choice("Then pause");
+        if allow_shutdown {
+            choice_action.add_choice("Then shut down");
+        }
+
+        let mut hpack = group::Pack::default()
+            .with_size(240, 30)
+            .center_of_parent()
+            .with_type(group::PackType::Horizontal);
+        hpack.set_spacing(20);
+
+        let mut button_cancel = button::Button::default()
+            .with_label("Cancel");
+        let mut button_confirm = button::Button::default()
+            .with_label("Start");
+
+        hpack.end();
+        hpack.auto_layout();
+        vpack.end();
+        win.end();
+
+        win.set_color(theme::window_color());
+        win.make_modal(true);
+        win.show();
+
+        choice_duration.set_value(1);
+        choice_action.set_value(0);
+        spinner_minutes.deactivate();
+
+        choice_duration.set_callback({
+            let mut spinner_minutes = spinner_minutes.clone();
+            move |c| {
+                if c.value() == 4 {
+                    spinner_minutes.activate();
+                } else {
+                    spinner_minutes.deactivate();
+                }
+            }
+        });
+
+        let is_confirm = Rc::new(Cell::new(false));
+
+        button_cancel.set_callback({
+            let mut win = win.clone();
+            let is_confirm_copy1 = is_confirm.clone();
+            move |_| {
+                win.hide();
+                is_confirm_copy1.set(false);
+            }
+        });
+
+        button_confirm.set_callback({
+            let mut win = win.clone();
+            let is_confirm_copy2 = is_confirm.clone();
+            move |_| {
+                win.hide();
+                is_confirm_copy2.set(true);
+            }
+        });
+
+        while win.shown() {
+            app::wait();
+        }
+
+        let confirm = is_confirm.get();
+
+        Self {
+            confirm,
+            choice_duration,
+            spinner_minutes,
+            choice_action,
+        }
+    }
+
+    pub fn value(&self) -> SleepOption {
+        if self.confirm {
+            match self.choice_duration.value() {
+                0 => SleepOption::Minutes(15),
+                1 => SleepOption::Minutes(30),
+                2 => SleepOption::Minutes(60),
+                3 => SleepOption::EndOfTrack,
+                4 => SleepOption::Minutes(self.spinner_minutes.value() as u32),
+                _ => SleepOption::Unknown,
+            }
+        } else {
+            SleepOption::Unknown
+        }
+    }
+
+    pub fn action(&self) -> SleepAction {
+        match self.choice_action.value() {
+            1 => SleepAction::Shutdown,
+            _ => SleepAction::Pause,
+        }
+    }
+}
+
+impl Default for SleepDialog {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+
+pub struct HotkeyDialog {
+    confirm: bool,
+    inputs: Vec<input::Input>,
+}
+
+impl HotkeyDialog {
+    pub fn new(hotkeys: &Hotkeys) -> Self {
+        let rows = HotkeyAction::ALL.len() as i32;
+        let mut win = window::Window::default()
+            .with_size(380, 120 + rows * 30)
+            .with_label("Global shortcuts")
+            .center_screen();
+
+        frame::Frame::new(10, 10, 360, 25, "Shortcuts like Ctrl+Alt+Right work in any window.")
+            .with_align(enums::Align::Inside | enums::Align::Left);
+        let inputs: Vec<input::Input> = HotkeyAction::ALL.iter()
+            .enumerate()
+            .map(|(i, action)| {
+                let mut input = input::Input::new(150, 45 + i as i32 * 30, 220, 25, None)
+                    .with_label(action.label());
+                input.set_value(hotkeys.shortcut(*action));
+                input.set_trigger(enums::CallbackTrigger::Changed);
+                input
+            })
+            .collect();
+        let mut frame_status = frame::Frame::new(10, 45 + rows * 30, 360, 25, None)
+            .with_align(enums::Align::Inside | enums::Align::Left | enums::Align::Clip);
+        frame_status.set_label_color(enums::Color::Red);
+
+        let mut button_cancel = button::Button::new(180, 80 + rows * 30, 90, 30, "Cancel");
+        let mut button_confirm = button::Button::new(280, 80 + rows * 30, 90, 30, "Save");
+
+        win.end();
+        win.set_color(theme::window_color());
+        win.make_modal(true);
+        win.show();
+
+        let mut validate = {
+            let inputs = inputs.clone();
+            let mut frame_status = frame_status.clone();
+            let mut button_confirm = button_confirm.clone();
+            move || {
+                let conflicts = Self::collect(&inputs).conflicts();
+                match conflicts.first() {
+                    Some(conflict) => {
+                        frame_status.set_label(conflict);
+                        button_confirm.deactivate();
+                    }
+                    None => {
+                        frame_status.set_label("");
+                        button_confirm.activate();
+                    }
+                }
+            }
+        };
+        validate();
+        for input in inputs.iter() {
+            let mut validate = validate.clone();
+            input.clone().set_callback(move |_| validate());
+        }
+
+        let is_confirm = Rc::new(Cell::new(false));
+
+        button_cancel.set_callback({
+            let mut win = win.clone();
+            let is_confirm_copy1 = is_confirm.clone();
+            move |_| {
+                win.hide();
+                is_confirm_copy1.set(false);
+            }
+        });
+
+        button_confirm.set_callback({
+            let mut win = win.clone();
+            let is_confirm_copy2 = is_confirm.clone();
+            move |_| {
+                win.hide();
+                is_confirm_copy2.set(true);
+            }
+        });
+
+        while win.shown() {
+            app::wait();
+        }
+
+        let confirm = is_confirm.get();
+
+        Self {
+            confirm,
+            inputs,
+        }
+    }
+
+    fn collect(inputs: &[input::Input]) -> Hotkeys {
+        Hotkeys {
+            hotkeys: HotkeyAction::ALL.iter()
+                .zip(inputs.iter())
+                .map(|(action, input)| Hotkey { action: *action, shortcut: input.value().trim().to_string() })
+                .collect(),
+        }
+    }
+
+    pub fn value(&self) -> Option<Hotkeys> {
+        if self.confirm {
+            Some(Self::collect(&self.inputs))
+        } else {
+            None
+        }
+    }
+}
+
+
+pub struct ProfileDialog {
+    confirm: bool,
+    profiles: Rc<RefCell<Vec<Profile>>>,
+}
+
+#[derive(Clone)]
+struct ProfileFields {
+    input_nickname: input::Input,
+    input_address: input::Input,
+    input_token: input::SecretInput,
+    check_volume: button::CheckButton,
+    spinner_volume: misc::Spinner,
+    check_shutdown: button::CheckButton,
+    check_reboot: button::CheckButton,
+}
+
+impl ProfileFields {
+    fn load(&mut self, profile: &Profile) {
+        self.input_nickname.set_value(&profile.nickname);
+        self.input_address.set_value(&profile.address);
+        self.input_token.set_value(profile.token.as_deref().unwrap_or(""));
+        self.check_volume.set_checked(profile.volume.is_some());
+        self.spinner_volume.set_value(profile.volume.unwrap_or(50) as f64);
+        self.check_shutdown.set_checked(profile.allow_shutdown);
+        self.check_reboot.set_checked(profile.allow_reboot);
+    }
+
+    fn store(&self, profile: &mut Profile) {
+        profile.nickname = self.input_nickname.value().trim().to_string();
+        profile.address = self.input_address.value().trim().to_string();
+        let token = self.input_token.value();
+        profile.token = if token.is_empty() { None } else { Some(token) };
+        profile.volume = if self.check_volume.is_checked() {
+            Some(self.spinner_volume.value() as u32)
+        } else {
+            None
+        };
+        profile.allow_shutdown = self.check_shutdown.is_checked();
+        profile.allow_reboot = self.check_reboot.is_checked();
+    }
+
+    fn set_active(&mut self, active: bool) {
+        let mut widgets: Vec<Box<dyn WidgetExt>> = vec![
+            Box::new(self.input_nickname.clone()),
+            Box::new(self.input_address.clone()),
+            Box::new(self.input_token.clone()),
+            Box::new(self.check_volume.clone()),
+            Box::new(self.spinner_volume.clone()),
+            Box::new(self.check_shutdown.clone()),
+            Box::new(self.check_reboot.clone()),
+        ];
+        for widget in widgets.iter_mut() {
+            if active {
+                widget.activate();
+            } else {
+                widget.deactivate();
+            }
+        }
+    }
+}
+
+impl ProfileDialog {
+    pub fn new(profiles: &[Profile]) -> Self {
+        let mut win = window::Window::default()
+            .with_size(480, 300)
+            .with_label("Profiles")
+            .center_screen();
+
+        let mut browser = browser::HoldBrowser::new(10, 10, 170, 240, None);
+        let mut button_add = button::Button::new(10, 260, 80, 30, "Add");
+        let mut button_remove = button::Button::new(100, 260, 80, 30, "Remove");
+
+        let input_nickname = input::Input::new(280, 10, 190, 25, "Nickname:");
+        let input_address = input::Input::new(280, 45, 190, 25, "Address:");
+        let input_token = input::SecretInput::new(280, 80, 190, 25, "Auth token:");
+        let check_volume = button::CheckButton::new(200, 115, 190, 25, "Set volume on connect");
+        let mut spinner_volume = misc::Spinner::new(410, 115, 60, 25, None);
+        spinner_volume.set_range(0.0, 100.0);
+        spinner_volume.set_step(1.0);
+        let check_shutdown = button::CheckButton::new(200, 150, 130, 25, "Allow shutdown");
+        let check_reboot = button::CheckButton::new(340, 150, 130, 25, "Allow reboot");
+
+        let mut button_cancel = button::Button::new(280, 260, 90, 30, "Cancel");
+        let mut button_confirm = button::Button::new(380, 260, 90, 30, "Save");
+
+        win.end();
+        win.set_color(theme::window_color());
+        win.make_modal(true);
+        win.show();
+
+        let mut fields = ProfileFields {
+            input_nickname,
+            input_address,
+            input_token,
+            check_volume,
+            spinner_volume,
+            check_shutdown,
+            check_reboot,
+        };
+
+        let profiles = Rc::new(RefCell::new(profiles.to_vec()));
+        let selected: Rc<Cell<Option<usize>>> = Rc::new(Cell::new(None));
+
+        let mut refresh = {
+            let profiles = profiles.clone();
+            let selected = selected.clone();
+            let mut fields = fields.clone();
+            let mut browser = browser.clone();
+            move || {
+                browser.clear();
+                for profile in profiles.borrow().iter() {
+                    // "@." stops the browser from reading format codes.
+                    browser.add(&format!("@.{}", profile.nickname));
+                }
+                match selected.get() {
+                    Some(idx) => {
+                        browser.select(idx as i32 + 1);
+                        fields.load(&profiles.borrow()[idx]);
+                        fields.set_active(true);
+                    }
+                    None => fields.set_active(false),
+                }
+            }
+        };
+        let store_selected = {
+            let profiles = profiles.clone();
+            let selected = selected.clone();
+            let fields = fields.clone();
+            move || {
+                if let Some(idx) = selected.get() {
+                    fields.store(&mut profiles.borrow_mut()[idx]);
+                }
+            }
+        };
+
+        if !profiles.borrow().is_empty() {
+            selected.set(Some(0));
+        }
+        fields.set_active(false);
+        refresh();
+
+        browser.set_callback({
+            let selected = selected.clone();
+            let store_selected = store_selected.clone();
+            let mut refresh = refresh.clone();
+            move |b| {
+                store_selected();
+                let line = b.value();
+                selected.set(if line > 0 { Some(line as usize - 1) } else { None });
+                refresh();
+            }
+        });
+
+        button_add.set_callback({
+            let profiles = profiles.clone();
+            let selected = selected.clone();
+            let store_selected = store_selected.clone();
+            let mut refresh = refresh.clone();
+            move |_| {
+                store_selected();
+                profiles.borrow_mut().push(Profile::default());
+                selected.set(Some(profiles.borrow().len() - 1));
+                refresh();
+            }
+        });
+
+        button_remove.set_callback({
+            let profiles = profiles.clone();
+            let selected = selected.clone();
+            let mut refresh = refresh.clone();
+            move |_| {
+                if let Some(idx) = selected.get() {
+                    profiles.borrow_mut().remove(idx);
+                    let len = profiles.borrow().len();
+                    selected.set(if len > 0 { Some(idx.min(len - 1)) } else { None });
+                    refresh();
+                }
+            }
+        });
+
+        let is_confirm = Rc::new(Cell::new(false));
+
+        button_cancel.set_callback({
+            let mut win = win.clone();
+            let is_confirm_copy1 = is_confirm.clone();
+            move |_| {
+                win.hide();
+                is_confirm_copy1.set(false);
+            }
+        });
+
+        button_confirm.set_callback({
+            let mut win = win.clone();
+            let is_confirm_copy2 = is_confirm.clone();
+            move |_| {
+                store_selected();
+                win.hide();
+                is_confirm_copy2.set(true);
+            }
+        });
+
+        while win.shown() {
+            app::wait();
+        }
+
+        let confirm = is_confirm.get();
+
+        Self {
+            confirm,
+            profiles,
+        }
+    }
+
+    /// The edited profiles, without those lacking a nickname or address.
+    pub fn value(&self) -> Option<Vec<Profile>> {
+        if self.confirm {
+            let mut profiles = self.profiles.borrow().clone();
+            profiles.retain(|p| !p.nickname.is_empty() && !p.address.is_empty());
+            Some(profiles)
+        } else {
+            None
+        }
+    }
+}
+
+
+pub struct NotificationDialog {
+    confirm: bool,
+    check_track_change: button::CheckButton,
+    check_connection_lost: button::CheckButton,
+    check_connection_restored: button::CheckButton,
+}
+
+impl NotificationDialog {
+    pub fn new(settings: &NotificationSettings) -> Self {
+        let mut win = window::Window::default()
+            .with_size(300, 170)
+            .with_label("Notifications")
+            .center_screen();
+
+        let mut vpack = group::Pack::default()
+            .with_size(240, 160)
+            .center_of_parent()
+            .with_type(group::PackType::Vertical);
+        vpack.set_spacing(5);
+
+        let check_track_change = button::CheckButton::default()
+            .with_size(0, 30)
+            .with_label("Track changes");
+        let check_connection_lost = button::CheckButton::default()
+            .with_size(0, 30)
+            .with_label("Connection lost");
+        let check_connection_restored = button::CheckButton::default()
+            .with_size(0, 30)
+            .with_label("Connection restored");
+
+        let mut hpack = group::Pack::default()
+            .with_size(240, 30)
+            .center_of_parent()
+            .with_type(group::PackType::Horizontal);
+        hpack.set_spacing(20);
+
+        let mut button_cancel = button::Button::default()
+            .with_label("Cancel");
+        let mut button_confirm = button::Button::default()
+            .with_label("Save");
+
+        hpack.end();
+        hpack.auto_layout();
+        vpack.end();
+        win.end();
+
+        win.set_color(theme::window_color());
+        win.make_modal(true);
+        win.show();
+
+        check_track_change.set_checked(settings.track_change);
+        check_connection_lost.set_checked(settings.connection_lost);
+        check_connection_restored.set_checked(settings.connection_restored);
+
+        let is_confirm = Rc::new(Cell::new(false));
+
+        button_cancel.set_callback({
+            let mut win = win.clone();
+            let is_confirm_copy1 = is_confirm.clone();
+            move |_| {
+                win.hide();
+                is_confirm_copy1.set(false);
+            }
+        });
+
+        button_confirm.set_callback({
+            let mut win = win.clone();
+            let is_confirm_copy2 = is_confirm.clone();
+            move |_| {
+                win.hide();
+                is_confirm_copy2.set(true);
+            }
+        });
+
+        while win.shown() {
+            app::wait();
+        }
+
+        let confirm = is_confirm.get();
+
+        Self {
+            confirm,
+            check_track_change,
+            check_connection_lost,
+            check_connection_restored,
+        }
+    }
+
+    pub fn value(&self) -> Option<NotificationSettings> {
+        if self.confirm {
+            Some(NotificationSettings {
+                track_change: self.check_track_change.is_checked(),
+                connection_lost: self.check_connection_lost.is_checked(),
+                connection_restored: self.check_connection_restored.is_checked(),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+
 pub struct AlertDialog {}
 
 impl AlertDialog {
     pub fn new(text: &str) -> Self {
+        // Longer messages, such as connection errors, get a wider window
+        // and wrap.
+        draw::set_font(enums::Font::Helvetica, app::font_size());
+        let (text_w, text_h) = draw::measure(text, false);
+        let label_w = text_w.clamp(240, 480);
+        let wrapped = (text_w + label_w - 1) / label_w;
+        let label_h = (text_h * wrapped.max(1) + 10).max(30);
+
         let mut win = window::Window::default()
-            .with_size(300, 85)
+            .with_size(label_w + 60, label_h + 55)
             .with_label("Alert")
             .center_screen();
 
         let vpack = group::Pack::default()
-            .with_size(240, 85)
+            .with_size(label_w, label_h + 55)
             .center_of_parent()
             .with_type(group::PackType::Vertical);       
 
         let _label = frame::Frame::default()
-            .with_size(0, 30)
-            .with_label(text);
+            .with_size(0, label_h)
+            .with_label(text)
+            .with_align(enums::Align::Center | enums::Align::Inside | enums::Align::Wrap);
 
         frame::Frame::default().with_size(0, 10);
         let mut button_ok = button::Button::default()
@@ -130,7 +1009,47 @@ impl AlertDialog {
         vpack.end();
         win.end();
         
-        win.set_color(enums::Color::White);
+        win.set_color(theme::window_color());
+        win.make_modal(true);
+        win.show();
+
+        button_ok.set_callback({
+            let mut win = win.clone();
+            move |_| {
+                win.hide();
+            }
+        });
+
+        while win.shown() {
+            app::wait();
+        }
+
+        Self {}
+    }
+}
+
+
+/// Read-only fixed-width text, such as the command line help.
+pub struct TextDialog {}
+
+impl TextDialog {
+    pub fn new(title: &str, text: &str) -> Self {
+        let mut win = window::Window::default()
+            .with_size(640, 420)
+            .with_label(title)
+            .center_screen();
+
+        let mut buffer = text::TextBuffer::default();
+        buffer.set_text(text);
+        let mut display = text::TextDisplay::new(10, 10, 620, 360, None);
+        display.set_buffer(buffer);
+        display.set_text_font(enums::Font::Courier);
+        display.set_text_size(12);
+
+        let mut button_ok = button::Button::new(540, 380, 90, 30, "OK");
+
+        win.end();
+        win.set_color(theme::window_color());
         win.make_modal(true);
         win.show();
 
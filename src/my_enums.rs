@@ -1,30 +1,224 @@
+use std::fmt;
 
-#[derive(Copy, Clone)]
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::commands::Command;
+
+
+#[derive(Clone)]
+pub enum AlbumArt {
+    Url(String),
+    Inline(Vec<u8>),
+}
+
+impl fmt::Debug for AlbumArt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlbumArt::Url(url) => f.debug_tuple("Url").field(url).finish(),
+            AlbumArt::Inline(bytes) => write!(f, "Inline({} bytes)", bytes.len()),
+        }
+    }
+}
+
+impl Serialize for AlbumArt {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        match self {
+            AlbumArt::Url(url) => map.serialize_entry("url", url)?,
+            AlbumArt::Inline(bytes) => map.serialize_entry("inline_bytes", &bytes.len())?,
+        }
+        map.end()
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct TrackInfo {
+    pub id: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub art: Option<AlbumArt>,
+    pub duration_ms: Option<u64>,
+    pub progress_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(tag = "event", content = "value", rename_all = "snake_case")]
 pub enum WSEventValue {
     Unknown,
     Missing,
     NotFound,
     Disconnect,
-    Connect(bool),
+    Connect,
+    ConnectFailed(ConnectError),
     Volume(i32),
     PrevTrack(bool),
     NextTrack(bool),
     TogglePlayPause(bool),
     ToggleShuffle(bool),
     ToggleRepeatState(bool),
+    Pause(bool),
+    Play(bool),
+    PlayPlaylist(bool),
+    SetSchedule(bool),
+    SaveTrack(bool),
+    RemoveSavedTrack(bool),
+    TrackSaved(bool),
+    NowPlaying(Option<TrackInfo>),
 }
 
-#[derive(Copy, Clone)]
+impl WSEventValue {
+    /// Error replies and failed commands. `TrackSaved(false)` is an answer,
+    /// not a failure.
+    pub fn is_error(&self) -> bool {
+        match self {
+            WSEventValue::Unknown
+            | WSEventValue::Missing
+            | WSEventValue::NotFound
+            | WSEventValue::ConnectFailed(_) => true,
+            WSEventValue::PrevTrack(ok)
+            | WSEventValue::NextTrack(ok)
+            | WSEventValue::TogglePlayPause(ok)
+            | WSEventValue::ToggleShuffle(ok)
+            | WSEventValue::ToggleRepeatState(ok)
+            | WSEventValue::Pause(ok)
+            | WSEventValue::Play(ok)
+            | WSEventValue::PlayPlaylist(ok)
+            | WSEventValue::SetSchedule(ok)
+            | WSEventValue::SaveTrack(ok)
+            | WSEventValue::RemoveSavedTrack(ok) => !ok,
+            WSEventValue::Disconnect
+            | WSEventValue::Connect
+            | WSEventValue::Volume(_)
+            | WSEventValue::TrackSaved(_)
+            | WSEventValue::NowPlaying(_) => false,
+        }
+    }
+}
+
+/// Why a connection attempt failed.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum ConnectError {
+    /// The host name could not be resolved.
+    Dns(String),
+    Refused,
+    Timeout,
+    Tls(String),
+    /// The server answered the WebSocket handshake with this HTTP status.
+    Rejected(u16),
+    Other(String),
+}
+
+impl ConnectError {
+    /// Few words for the status bar.
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            ConnectError::Dns(_) => "Unknown host",
+            ConnectError::Refused => "Refused",
+            ConnectError::Timeout => "Timed out",
+            ConnectError::Tls(_) => "TLS error",
+            ConnectError::Rejected(_) => "Rejected",
+            ConnectError::Other(_) => "Connect failed",
+        }
+    }
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectError::Dns(e) => write!(f, "Could not resolve the host name ({})", e),
+            ConnectError::Refused => write!(f, "Connection refused; is the SpotifyPi server running?"),
+            ConnectError::Timeout => write!(f, "Connection timed out"),
+            ConnectError::Tls(e) => write!(f, "TLS error: {}", e),
+            ConnectError::Rejected(status) => {
+                let reason = tokio_tungstenite::tungstenite::http::StatusCode::from_u16(*status)
+                    .ok()
+                    .and_then(|status| status.canonical_reason())
+                    .unwrap_or("");
+                write!(f, "Handshake rejected with HTTP {} {}", status, reason)
+            }
+            ConnectError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum FrameDirection {
+    Sent,
+    Received,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum CommandResult {
+    Ok,
+    Failed,
+    Missing,
+    NotFound,
+    Timeout,
+    Disconnected,
+}
+
+impl CommandResult {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandResult::Ok => "ok",
+            CommandResult::Failed => "failed",
+            CommandResult::Missing => "missing argument",
+            CommandResult::NotFound => "not found",
+            CommandResult::Timeout => "timed out",
+            CommandResult::Disconnected => "disconnected",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SceneStepReport {
+    pub scene: String,
+    pub step: usize,
+    pub total: usize,
+    pub command: String,
+    pub result: CommandResult,
+}
+
+#[derive(Clone)]
 pub enum MyAppMessage {
     ClickConnect,
+    ClickDisconnect,
+    AddressChanged,
     PrevTrack,
     NextTrack,
     PlayPause,
     ToggleShuffle,
     ToggleRepeat,
+    ToggleSaved,
     ClickPower,
+    ClickSleep,
+    ClickAlarms,
+    RunScene(usize),
+    SceneStep(SceneStepReport),
+    ClickScripts,
+    ScriptReload,
+    ScriptLog(String),
+    ScriptError(String),
+    ScriptCommand(Command),
+    SendCommand(Command),
+    ClickHotkeys,
+    ClickNotifications,
+    ClickProfiles,
+    SetTheme(usize),
+    SetConnectTimeout(u64),
+    ShowWindow,
+    CloseWindow,
+    Quit,
+    StepVolume(i32),
     ChangeVolume,
+    FlushVolume,
+    Tick,
     WSEventValue(WSEventValue),
+    Frame(FrameDirection, String),
+    ClickTraffic,
+    AlbumArtLoaded(String, Option<std::path::PathBuf>),
 }
 
 #[derive(Copy, Clone)]
@@ -33,3 +227,16 @@ pub enum PowerOption {
     Reboot,
     Unknown,
 }
+
+#[derive(Copy, Clone)]
+pub enum SleepOption {
+    Minutes(u32),
+    EndOfTrack,
+    Unknown,
+}
+
+#[derive(Copy, Clone)]
+pub enum SleepAction {
+    Pause,
+    Shutdown,
+}
@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::my_enums::TrackInfo;
+
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub track_change: bool,
+    pub connection_lost: bool,
+    pub connection_restored: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            track_change: true,
+            connection_lost: true,
+            connection_restored: true,
+        }
+    }
+}
+
+struct Notification {
+    summary: String,
+    body: String,
+    image: Option<PathBuf>,
+    /// Track notifications replace each other instead of piling up.
+    is_track: bool,
+}
+
+/// Sends desktop notifications through the freedesktop notification
+/// service. Only available on Linux.
+pub struct Notifier {
+    pub settings: NotificationSettings,
+    #[cfg(target_os = "linux")]
+    tx: tokio::sync::mpsc::UnboundedSender<Notification>,
+}
+
+impl Notifier {
+    pub fn new(settings: NotificationSettings) -> Self {
+        Self {
+            settings,
+            #[cfg(target_os = "linux")]
+            tx: freedesktop::start(),
+        }
+    }
+
+    pub fn is_supported() -> bool {
+        cfg!(target_os = "linux")
+    }
+
+    pub fn track_changed(&self, track: &TrackInfo, art: Option<&Path>) {
+        if !self.settings.track_change {
+            return;
+        }
+        let body = if track.album.is_empty() {
+            track.artist.clone()
+        } else {
+            format!("{}\n{}", track.artist, track.album)
+        };
+        self.send(Notification {
+            summary: track.title.clone(),
+            body,
+            image: art.map(Path::to_path_buf),
+            is_track: true,
+        });
+    }
+
+    pub fn connection_lost(&self) {
+        if self.settings.connection_lost {
+            self.send(Notification {
+                summary: "SpotifyPi disconnected".to_string(),
+                body: "The connection to SpotifyPi was closed.".to_string(),
+                image: None,
+                is_track: false,
+            });
+        }
+    }
+
+    pub fn connection_restored(&self) {
+        if self.settings.connection_restored {
+            self.send(Notification {
+                summary: "SpotifyPi connected".to_string(),
+                body: "The connection to SpotifyPi was restored.".to_string(),
+                image: None,
+                is_track: false,
+            });
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn send(&self, notification: Notification) {
+        let _ = self.tx.send(notification);
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn send(&self, _notification: Notification) {}
+}
+
+#[cfg(target_os = "linux")]
+mod freedesktop {
+    use std::collections::HashMap;
+
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+    use tracing::warn;
+    use zbus::zvariant::Value;
+    use zbus::{dbus_proxy, Connection};
+
+    use super::Notification;
+
+
+    const APP_NAME: &str = "SpotifyPi Control Panel";
+    const EXPIRE_TIMEOUT_MS: i32 = 5000;
+
+    #[dbus_proxy(
+        interface = "org.freedesktop.Notifications",
+        default_service = "org.freedesktop.Notifications",
+        default_path = "/org/freedesktop/Notifications"
+    )]
+    trait Notifications {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            app_name: &str,
+            replaces_id: u32,
+            app_icon: &str,
+            summary: &str,
+            body: &str,
+            actions: &[&str],
+            hints: HashMap<&str, Value<'_>>,
+            expire_timeout: i32,
+        ) -> zbus::Result<u32>;
+    }
+
+    pub(super) fn start() -> UnboundedSender<Notification> {
+        let (tx, mut rx) = unbounded_channel::<Notification>();
+        tokio::spawn(async move {
+            let mut proxy: Option<NotificationsProxy<'static>> = None;
+            let mut track_id = 0;
+            while let Some(notification) = rx.recv().await {
+                if proxy.is_none() {
+                    proxy = match connect().await {
+                        Ok(p) => Some(p),
+                        Err(e) => {
+                            warn!("Notifications are not available: {}", e);
+                            continue;
+                        }
+                    };
+                }
+                let p = proxy.as_ref().unwrap();
+                let replaces_id = if notification.is_track { track_id } else { 0 };
+                let image = notification.image
+                    .as_ref()
+                    .map(|path| path.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let mut hints = HashMap::new();
+                if notification.is_track {
+                    hints.insert("category", Value::from("x-gnome.music"));
+                    hints.insert("transient", Value::from(true));
+                }
+                if !image.is_empty() {
+                    hints.insert("image-path", Value::from(image.as_str()));
+                }
+                let body = escape_markup(&notification.body);
+                match p.notify(APP_NAME, replaces_id, "", &notification.summary, &body, &[], hints, EXPIRE_TIMEOUT_MS).await {
+                    Ok(id) => {
+                        if notification.is_track {
+                            track_id = id;
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Failed to send notification: {}", e);
+                        proxy = None;
+                    }
+                }
+            }
+        });
+        tx
+    }
+
+    async fn connect() -> zbus::Result<NotificationsProxy<'static>> {
+        let connection = Connection::session().await?;
+        NotificationsProxy::new(&connection).await
+    }
+
+    /// Notification bodies may be rendered as markup.
+    fn escape_markup(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+}
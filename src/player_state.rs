@@ -0,0 +1,40 @@
+use serde::Serialize;
+
+use crate::my_enums::{TrackInfo, WSEventValue};
+
+
+/// Last known state of the player, built up from received events.
+#[derive(Clone, Default, Serialize)]
+pub struct PlayerState {
+    pub connected: bool,
+    pub volume: Option<i32>,
+    pub now_playing: Option<TrackInfo>,
+    pub track_saved: Option<bool>,
+}
+
+impl PlayerState {
+    /// Applies `event`, returning whether the state changed.
+    pub fn apply(&mut self, event: &WSEventValue) -> bool {
+        match event {
+            WSEventValue::Connect => self.connected = true,
+            WSEventValue::ConnectFailed(_) => self.connected = false,
+            WSEventValue::Disconnect => *self = PlayerState::default(),
+            WSEventValue::Volume(volume) => self.volume = Some(*volume),
+            WSEventValue::NowPlaying(track) => {
+                let same_track = match (&self.now_playing, track) {
+                    (Some(old), Some(new)) => old.id == new.id,
+                    _ => false,
+                };
+                if !same_track {
+                    self.track_saved = None;
+                }
+                self.now_playing = track.clone();
+            }
+            WSEventValue::TrackSaved(saved) => self.track_saved = Some(*saved),
+            WSEventValue::SaveTrack(true) => self.track_saved = Some(true),
+            WSEventValue::RemoveSavedTrack(true) => self.track_saved = Some(false),
+            _ => return false,
+        }
+        true
+    }
+}
@@ -0,0 +1,30 @@
+use std::time::{Duration, Instant};
+
+
+/// Linear volume change from `from` to `to` over `duration`.
+pub struct VolumeRamp {
+    from: u32,
+    to: u32,
+    start: Instant,
+    duration: Duration,
+}
+
+impl VolumeRamp {
+    pub fn new(from: u32, to: u32, start: Instant, duration: Duration) -> Self {
+        Self { from, to, start, duration }
+    }
+
+    pub fn volume_at(&self, now: Instant) -> u32 {
+        if self.duration.is_zero() || now >= self.start + self.duration {
+            return self.to;
+        }
+        let elapsed = now.saturating_duration_since(self.start).as_secs_f64();
+        let progress = elapsed / self.duration.as_secs_f64();
+        let volume = self.from as f64 + (self.to as f64 - self.from as f64) * progress;
+        volume.round() as u32
+    }
+
+    pub fn is_finished(&self, now: Instant) -> bool {
+        now >= self.start + self.duration
+    }
+}
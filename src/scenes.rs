@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::commands::Command;
+use crate::config::load_toml;
+
+
+const SCENES_FILE: &str = "scenes.toml";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SceneStep {
+    pub command: Command,
+    /// Wait before sending the command.
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+/// A named sequence of commands, e.g.
+///
+/// ```toml
+/// [[scenes]]
+/// name = "Focus"
+/// steps = [
+///     { command = "set_volume 20" },
+///     { command = "toggle_shuffle" },
+///     { command = "play_playlist spotify:playlist:37i9dQZF1DWZeKCadgRdKQ", delay_ms = 500 },
+/// ]
+/// ```
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scene {
+    pub name: String,
+    pub steps: Vec<SceneStep>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Scenes {
+    #[serde(default)]
+    pub scenes: Vec<Scene>,
+}
+
+impl Scenes {
+    pub fn load() -> Self {
+        load_toml(SCENES_FILE)
+    }
+}
@@ -0,0 +1,372 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::commands::Command;
+use crate::config::{load_toml, save_toml};
+use crate::ramp::VolumeRamp;
+
+
+const SCHEDULE_FILE: &str = "schedule.toml";
+
+pub const WEEKDAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+/// Alarms that could not start by then, e.g. because the computer was
+/// asleep or the panel not connected, are dropped.
+const MAX_ALARM_DELAY: chrono::Duration = chrono::Duration::minutes(30);
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlarmHost {
+    Panel,
+    Pi,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Alarm {
+    pub enabled: bool,
+    pub hour: u32,
+    pub minute: u32,
+    /// Monday first.
+    pub weekdays: [bool; 7],
+    pub playlist_uri: String,
+    pub volume: u32,
+    pub ramp_minutes: u32,
+    pub host: AlarmHost,
+}
+
+impl Default for Alarm {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hour: 7,
+            minute: 0,
+            weekdays: [true, true, true, true, true, false, false],
+            playlist_uri: String::new(),
+            volume: 40,
+            ramp_minutes: 5,
+            host: AlarmHost::Panel,
+        }
+    }
+}
+
+impl Alarm {
+    pub fn summary(&self) -> String {
+        let days: Vec<&str> = WEEKDAY_NAMES.iter()
+            .zip(self.weekdays.iter())
+            .filter(|(_, on)| **on)
+            .map(|(name, _)| *name)
+            .collect();
+        let state = if self.enabled { "" } else { " (off)" };
+        format!("{:02}:{:02} {}{}", self.hour, self.minute, days.join(","), state)
+    }
+
+    /// When the alarm goes off on `date`, if it does. A time skipped by a
+    /// daylight saving change goes off when the clock has jumped ahead.
+    fn time_on<Tz: TimeZone>(&self, date: NaiveDate, tz: &Tz) -> Option<DateTime<Tz>> {
+        if !self.enabled
+            || self.host != AlarmHost::Panel
+            || !self.weekdays[date.weekday().num_days_from_monday() as usize]
+        {
+            return None;
+        }
+        let mut time = date.and_hms_opt(self.hour, self.minute, 0)?;
+        // Gaps last an hour or less, but a day bounds the search.
+        for _ in 0..24 * 60 {
+            if let Some(time) = tz.from_local_datetime(&time).earliest() {
+                return Some(time);
+            }
+            time += chrono::Duration::minutes(1);
+        }
+        None
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Schedule {
+    #[serde(default)]
+    pub alarms: Vec<Alarm>,
+}
+
+impl Schedule {
+    pub fn load() -> Self {
+        load_toml(SCHEDULE_FILE)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_toml(SCHEDULE_FILE, self)
+    }
+
+    /// Command that stores the Pi-hosted alarms on the server, so they fire
+    /// without the panel running.
+    pub fn push_command(&self) -> Command {
+        let alarms: Vec<&Alarm> = self.alarms.iter()
+            .filter(|a| a.host == AlarmHost::Pi)
+            .collect();
+        let json = serde_json::to_string(&alarms).unwrap_or_else(|_| "[]".to_string());
+        Command::SetSchedule(json)
+    }
+}
+
+/// Fires panel-hosted alarms. Every alarm time since the last check counts,
+/// so alarms are not lost when ticks are held up by a modal dialog or the
+/// computer sleeping.
+#[derive(Default)]
+pub struct Scheduler {
+    last_check: Option<DateTime<Local>>,
+    /// Alarms that went off and wait to be started, with their time.
+    waiting: Vec<(Alarm, DateTime<Local>)>,
+}
+
+impl Scheduler {
+    /// Returns the alarms to start now. While `can_start` is false, e.g.
+    /// when not connected, due alarms wait for up to `MAX_ALARM_DELAY`.
+    pub fn due_alarms(&mut self, schedule: &Schedule, now: DateTime<Local>, can_start: bool) -> Vec<Alarm> {
+        // The first check covers the current minute.
+        let since = self.last_check.unwrap_or_else(|| {
+            now - chrono::Duration::seconds(now.second() as i64 + 1)
+        });
+        let since = since.max(now - MAX_ALARM_DELAY);
+        if since < now {
+            let mut date = since.date_naive();
+            while date <= now.date_naive() {
+                for alarm in &schedule.alarms {
+                    match alarm.time_on(date, &Local) {
+                        Some(time) if since < time && time <= now => {
+                            if !can_start {
+                                info!("alarm {}: waiting for a connection", alarm.summary());
+                            }
+                            self.waiting.push((alarm.clone(), time));
+                        }
+                        _ => {}
+                    }
+                }
+                date = match date.succ_opt() {
+                    Some(next) => next,
+                    None => break,
+                };
+            }
+        }
+        // A clock set back does not repeat alarms already checked.
+        self.last_check = Some(self.last_check.map_or(now, |last| last.max(now)));
+
+        self.waiting.retain(|(alarm, time)| {
+            let keep = now - *time <= MAX_ALARM_DELAY;
+            if !keep {
+                warn!("alarm {}: dropped, could not start in time", alarm.summary());
+            }
+            keep
+        });
+        if !can_start {
+            return Vec::new();
+        }
+        self.waiting.drain(..).map(|(alarm, _)| alarm).collect()
+    }
+}
+
+/// Volume ramp-up of an alarm that is currently playing.
+pub struct AlarmRun {
+    ramp: VolumeRamp,
+    last_volume: u32,
+}
+
+impl AlarmRun {
+    pub fn new(alarm: &Alarm) -> Self {
+        let duration = Duration::from_secs(alarm.ramp_minutes as u64 * 60);
+        Self {
+            ramp: VolumeRamp::new(0, alarm.volume, Instant::now(), duration),
+            last_volume: 0,
+        }
+    }
+
+    /// Returns the next volume to set, if it changed since the last call.
+    pub fn tick(&mut self, now: Instant) -> Option<u32> {
+        let volume = self.ramp.volume_at(now);
+        if volume == self.last_volume {
+            return None;
+        }
+        self.last_volume = volume;
+        Some(volume)
+    }
+
+    pub fn is_finished(&self, now: Instant) -> bool {
+        self.ramp.is_finished(now)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, LocalResult, NaiveDateTime};
+
+    use super::*;
+
+    fn daily(hour: u32, minute: u32) -> Schedule {
+        Schedule {
+            alarms: vec![Alarm {
+                hour,
+                minute,
+                weekdays: [true; 7],
+                ..Alarm::default()
+            }],
+        }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32, second: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 6, day, hour, minute, second).unwrap()
+    }
+
+    #[test]
+    fn first_check_covers_current_minute() {
+        let mut scheduler = Scheduler::default();
+        assert_eq!(scheduler.due_alarms(&daily(7, 0), at(10, 7, 0, 30), true).len(), 1);
+    }
+
+    #[test]
+    fn fires_once() {
+        let schedule = daily(7, 0);
+        let mut scheduler = Scheduler::default();
+        assert!(scheduler.due_alarms(&schedule, at(10, 6, 59, 30), true).is_empty());
+        assert_eq!(scheduler.due_alarms(&schedule, at(10, 7, 0, 10), true).len(), 1);
+        assert!(scheduler.due_alarms(&schedule, at(10, 7, 0, 50), true).is_empty());
+        assert!(scheduler.due_alarms(&schedule, at(10, 7, 1, 10), true).is_empty());
+    }
+
+    #[test]
+    fn catches_up_missed_checks() {
+        let schedule = daily(7, 0);
+        let mut scheduler = Scheduler::default();
+        scheduler.due_alarms(&schedule, at(10, 6, 50, 0), true);
+        assert_eq!(scheduler.due_alarms(&schedule, at(10, 7, 20, 0), true).len(), 1);
+    }
+
+    #[test]
+    fn catch_up_is_limited() {
+        let schedule = daily(7, 0);
+        let mut scheduler = Scheduler::default();
+        scheduler.due_alarms(&schedule, at(10, 6, 50, 0), true);
+        assert!(scheduler.due_alarms(&schedule, at(10, 7, 40, 0), true).is_empty());
+    }
+
+    #[test]
+    fn catches_up_across_midnight() {
+        let schedule = daily(23, 55);
+        let mut scheduler = Scheduler::default();
+        scheduler.due_alarms(&schedule, at(10, 23, 50, 0), true);
+        assert_eq!(scheduler.due_alarms(&schedule, at(11, 0, 10, 0), true).len(), 1);
+    }
+
+    #[test]
+    fn waits_while_disconnected() {
+        let schedule = daily(7, 0);
+        let mut scheduler = Scheduler::default();
+        assert!(scheduler.due_alarms(&schedule, at(10, 7, 0, 30), false).is_empty());
+        assert!(scheduler.due_alarms(&schedule, at(10, 7, 5, 0), false).is_empty());
+        assert_eq!(scheduler.due_alarms(&schedule, at(10, 7, 10, 0), true).len(), 1);
+        assert!(scheduler.due_alarms(&schedule, at(10, 7, 11, 0), true).is_empty());
+    }
+
+    #[test]
+    fn drops_alarms_waiting_too_long() {
+        let schedule = daily(7, 0);
+        let mut scheduler = Scheduler::default();
+        assert!(scheduler.due_alarms(&schedule, at(10, 7, 0, 30), false).is_empty());
+        assert!(scheduler.due_alarms(&schedule, at(10, 7, 31, 0), true).is_empty());
+    }
+
+    #[test]
+    fn clock_set_back_does_not_repeat() {
+        let schedule = daily(7, 0);
+        let mut scheduler = Scheduler::default();
+        assert_eq!(scheduler.due_alarms(&schedule, at(10, 7, 0, 30), true).len(), 1);
+        assert!(scheduler.due_alarms(&schedule, at(10, 6, 59, 0), true).is_empty());
+        assert!(scheduler.due_alarms(&schedule, at(10, 7, 0, 30), true).is_empty());
+    }
+
+    #[test]
+    fn skips_other_alarms() {
+        let mut schedule = daily(7, 0);
+        // 2026-06-10 is a Wednesday.
+        schedule.alarms[0].weekdays = [true, true, false, true, true, true, true];
+        let mut scheduler = Scheduler::default();
+        assert!(scheduler.due_alarms(&schedule, at(10, 7, 0, 30), true).is_empty());
+
+        let mut schedule = daily(7, 0);
+        schedule.alarms[0].host = AlarmHost::Pi;
+        let mut scheduler = Scheduler::default();
+        assert!(scheduler.due_alarms(&schedule, at(10, 7, 0, 30), true).is_empty());
+
+        let mut schedule = daily(7, 0);
+        schedule.alarms[0].enabled = false;
+        let mut scheduler = Scheduler::default();
+        assert!(scheduler.due_alarms(&schedule, at(10, 7, 0, 30), true).is_empty());
+    }
+
+    /// UTC+1, moving to UTC+2 at 02:00 on 2026-03-29, so 02:00 to 03:00
+    /// does not exist that day.
+    #[derive(Clone)]
+    struct SpringForward;
+
+    impl SpringForward {
+        const CHANGE: (u32, u32) = (3, 29);
+
+        fn offset(summer: bool) -> FixedOffset {
+            FixedOffset::east_opt(if summer { 7200 } else { 3600 }).unwrap()
+        }
+
+        fn is_summer(date: NaiveDate) -> bool {
+            (date.month(), date.day()) > Self::CHANGE
+        }
+    }
+
+    impl TimeZone for SpringForward {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            SpringForward
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            LocalResult::Single(Self::offset(Self::is_summer(*local)))
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let date = local.date();
+            if (date.month(), date.day()) != Self::CHANGE {
+                return LocalResult::Single(Self::offset(Self::is_summer(date)));
+            }
+            match local.hour() {
+                0..=1 => LocalResult::Single(Self::offset(false)),
+                2 => LocalResult::None,
+                _ => LocalResult::Single(Self::offset(true)),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            Self::offset(Self::is_summer(*utc))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let date = utc.date();
+            let summer = if (date.month(), date.day()) == Self::CHANGE {
+                utc.hour() >= 1
+            } else {
+                Self::is_summer(date)
+            };
+            Self::offset(summer)
+        }
+    }
+
+    #[test]
+    fn time_in_daylight_saving_gap_moves_forward() {
+        let alarm = &daily(2, 30).alarms[0];
+        let date = NaiveDate::from_ymd_opt(2026, 3, 29).unwrap();
+        let time = alarm.time_on(date, &SpringForward).unwrap();
+        assert_eq!(time.naive_local(), date.and_hms_opt(3, 0, 0).unwrap());
+
+        let before = &daily(1, 30).alarms[0];
+        let time = before.time_on(date, &SpringForward).unwrap();
+        assert_eq!(time.naive_local(), date.and_hms_opt(1, 30, 0).unwrap());
+    }
+}
@@ -0,0 +1,90 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use fltk::{prelude::*, *};
+
+use crate::my_enums::MyAppMessage;
+use crate::theme;
+
+
+const MAX_LINES: i32 = 2000;
+
+pub struct ScriptConsole {
+    win: window::Window,
+    buffer: text::TextBuffer,
+    display: text::TextDisplay,
+    /// Lines in `buffer`, kept to scroll without counting them.
+    lines: Rc<Cell<i32>>,
+}
+
+impl ScriptConsole {
+    pub fn new(app_msg_sender: app::Sender<MyAppMessage>) -> Self {
+        let mut win = window::Window::default()
+            .with_size(560, 300)
+            .with_label("Script console");
+
+        let mut panel = group::Flex::default_fill().column();
+        let buffer = text::TextBuffer::default();
+        let mut display = text::TextDisplay::default();
+        display.set_buffer(buffer.clone());
+        display.set_text_font(enums::Font::Courier);
+        display.set_text_size(12);
+
+        let mut row = group::Flex::default().row();
+        frame::Frame::default();
+        let mut button_clear = button::Button::default().with_label("Clear");
+        let mut button_reload = button::Button::default().with_label("Reload scripts");
+        row.set_size(&button_clear, 90);
+        row.set_size(&button_reload, 120);
+        row.end();
+
+        panel.set_size(&row, 30);
+        panel.end();
+
+        win.resizable(&panel);
+        win.set_color(theme::window_color());
+        win.end();
+
+        let lines = Rc::new(Cell::new(0));
+        button_clear.set_callback({
+            let mut buffer = buffer.clone();
+            let lines = lines.clone();
+            move |_| {
+                buffer.set_text("");
+                lines.set(0);
+            }
+        });
+        button_reload.emit(app_msg_sender, MyAppMessage::ScriptReload);
+
+        Self {
+            win,
+            buffer,
+            display,
+            lines,
+        }
+    }
+
+    pub fn show(&mut self) {
+        self.win.show();
+        self.display.scroll(self.lines.get(), 0);
+    }
+
+    pub fn append(&mut self, line: &str) {
+        let time = chrono::Local::now().format("%H:%M:%S");
+        let text = format!("{} {}\n", time, line);
+        // `position_to_xy` leaves positions outside the view at 0, 0.
+        let at_end = self.display.position_to_xy(self.buffer.length()).1 != 0;
+        self.buffer.append(&text);
+        self.lines.set(self.lines.get() + text.matches('\n').count() as i32);
+        while self.lines.get() > MAX_LINES {
+            match self.buffer.find_char_forward(0, '\n') {
+                Some(end) => self.buffer.remove(0, end + 1),
+                None => break,
+            }
+            self.lines.set(self.lines.get() - 1);
+        }
+        if at_end {
+            self.display.scroll(self.lines.get(), 0);
+        }
+    }
+}
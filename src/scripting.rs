@@ -0,0 +1,202 @@
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+
+use chrono::{Datelike, Local, Timelike};
+use fltk::app::Sender;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Scope, AST};
+
+use crate::commands::Command;
+use crate::config::config_dir;
+use crate::my_enums::{MyAppMessage, WSEventValue};
+
+
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+pub enum ScriptInput {
+    Event(WSEventValue),
+    Tick,
+    Reload,
+}
+
+pub fn scripts_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("scripts"))
+}
+
+/// Starts the script runtime on its own thread. Scripts are the `*.rhai`
+/// files in the scripts dir; they may define any of the callbacks
+/// `on_connect()`, `on_disconnect()`, `on_volume(volume)`,
+/// `on_track(title, artist, album)` and `on_tick()` (called every minute).
+pub fn start_scripting(output_tx: Sender<MyAppMessage>) -> mpsc::Sender<ScriptInput> {
+    let (input_tx, input_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut runtime = ScriptRuntime::new(output_tx);
+        runtime.load();
+        for input in input_rx {
+            runtime.handle(input);
+        }
+    });
+    input_tx
+}
+
+struct Script {
+    name: String,
+    ast: AST,
+    scope: Scope<'static>,
+}
+
+struct ScriptRuntime {
+    engine: Engine,
+    scripts: Vec<Script>,
+    volume: Rc<Cell<i64>>,
+    output_tx: Sender<MyAppMessage>,
+}
+
+impl ScriptRuntime {
+    fn new(output_tx: Sender<MyAppMessage>) -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let volume = Rc::new(Cell::new(-1));
+
+        engine.on_print({
+            let output_tx = output_tx.clone();
+            move |text| output_tx.send(MyAppMessage::ScriptLog(text.to_string()))
+        });
+
+        let simple_commands = [
+            ("prev_track", Command::PrevTrack),
+            ("next_track", Command::NextTrack),
+            ("play_pause", Command::TogglePlayPause),
+            ("play", Command::Play),
+            ("pause", Command::Pause),
+            ("toggle_shuffle", Command::ToggleShuffle),
+            ("toggle_repeat", Command::ToggleRepeatState),
+            ("save_track", Command::SaveTrack),
+            ("remove_saved_track", Command::RemoveSavedTrack),
+        ];
+        for (name, command) in simple_commands {
+            let output_tx = output_tx.clone();
+            engine.register_fn(name, move || output_tx.send(MyAppMessage::ScriptCommand(command.clone())));
+        }
+        engine.register_fn("set_volume", {
+            let output_tx = output_tx.clone();
+            move |volume: i64| {
+                let volume = volume.clamp(0, 100) as u32;
+                output_tx.send(MyAppMessage::ScriptCommand(Command::SetVolume(volume)));
+            }
+        });
+        engine.register_fn("play_playlist", {
+            let output_tx = output_tx.clone();
+            move |uri: &str| output_tx.send(MyAppMessage::ScriptCommand(Command::PlayPlaylist(uri.to_string())))
+        });
+        engine.register_fn("send", {
+            let output_tx = output_tx.clone();
+            move |text: &str| -> Result<(), Box<EvalAltResult>> {
+                let command = Command::parse(text)?;
+                output_tx.send(MyAppMessage::ScriptCommand(command));
+                Ok(())
+            }
+        });
+        engine.register_fn("volume", {
+            let volume = volume.clone();
+            move || volume.get()
+        });
+        engine.register_fn("hour", || Local::now().hour() as i64);
+        engine.register_fn("minute", || Local::now().minute() as i64);
+        engine.register_fn("weekday", || Local::now().weekday().number_from_monday() as i64);
+
+        Self {
+            engine,
+            scripts: Vec::new(),
+            volume,
+            output_tx,
+        }
+    }
+
+    fn load(&mut self) {
+        self.scripts.clear();
+        let dir = match scripts_dir() {
+            Some(dir) => dir,
+            None => return,
+        };
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "rhai"))
+                .collect(),
+            Err(_) => {
+                self.log(format!("No scripts in {}", dir.display()));
+                return;
+            }
+        };
+        paths.sort();
+
+        for path in paths {
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let ast = match self.engine.compile_file(path) {
+                Ok(ast) => ast,
+                Err(e) => {
+                    self.error(format!("{}: {}", name, e));
+                    continue;
+                }
+            };
+            let mut scope = Scope::new();
+            if let Err(e) = self.engine.run_ast_with_scope(&mut scope, &ast) {
+                self.error(format!("{}: {}", name, e));
+                continue;
+            }
+            self.log(format!("Loaded {}", name));
+            self.scripts.push(Script { name, ast, scope });
+        }
+    }
+
+    fn handle(&mut self, input: ScriptInput) {
+        match input {
+            ScriptInput::Event(event) => match event {
+                WSEventValue::Connect => self.call("on_connect", vec![]),
+                WSEventValue::Disconnect => self.call("on_disconnect", vec![]),
+                WSEventValue::Volume(volume) => {
+                    self.volume.set(volume as i64);
+                    self.call("on_volume", vec![Dynamic::from(volume as i64)]);
+                }
+                WSEventValue::NowPlaying(Some(track)) => {
+                    let args = vec![track.title.into(), track.artist.into(), track.album.into()];
+                    self.call("on_track", args);
+                }
+                _ => {}
+            },
+            ScriptInput::Tick => self.call("on_tick", vec![]),
+            ScriptInput::Reload => self.load(),
+        }
+    }
+
+    fn call(&mut self, fn_name: &str, args: Vec<Dynamic>) {
+        let mut errors = Vec::new();
+        for script in self.scripts.iter_mut() {
+            let defined = script.ast.iter_functions()
+                .any(|f| f.name == fn_name && f.params.len() == args.len());
+            if !defined {
+                continue;
+            }
+            // The top-level statements ran once in `load`.
+            let options = CallFnOptions::new().eval_ast(false);
+            let result = self.engine.call_fn_with_options::<Dynamic>(options, &mut script.scope, &script.ast, fn_name, args.clone());
+            if let Err(e) = result {
+                errors.push(format!("{}: {}: {}", script.name, fn_name, e));
+            }
+        }
+        for error in errors {
+            self.error(error);
+        }
+    }
+
+    fn log(&self, text: String) {
+        self.output_tx.send(MyAppMessage::ScriptLog(text));
+    }
+
+    fn error(&self, text: String) {
+        self.output_tx.send(MyAppMessage::ScriptError(text));
+    }
+}
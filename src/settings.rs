@@ -0,0 +1,197 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config::{config_dir, load_toml, save_toml};
+use crate::hotkeys::Hotkeys;
+use crate::my_enums::PowerOption;
+use crate::notifications::NotificationSettings;
+use crate::theme::Theme;
+
+
+const SETTINGS_FILE: &str = "settings.toml";
+/// Bumped whenever a change to `Settings` needs `migrate` to rewrite older
+/// files.
+pub const SETTINGS_VERSION: u32 = 2;
+const MAX_RECENT_ADDRESSES: usize = 10;
+
+/// A named device with its own connection and safety settings.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub nickname: String,
+    pub address: String,
+    /// Sent as a bearer token in the WebSocket handshake.
+    pub token: Option<String>,
+    /// Volume set right after connecting.
+    pub volume: Option<u32>,
+    pub allow_shutdown: bool,
+    pub allow_reboot: bool,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            nickname: "New device".to_string(),
+            address: "spotifypi.local:9487".to_string(),
+            token: None,
+            volume: None,
+            allow_shutdown: true,
+            allow_reboot: true,
+        }
+    }
+}
+
+impl Profile {
+    pub fn allows(&self, option: PowerOption) -> bool {
+        match option {
+            PowerOption::Shutdown => self.allow_shutdown,
+            PowerOption::Reboot => self.allow_reboot,
+            PowerOption::Unknown => false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+/// Everything the panel remembers between launches, in `settings.toml`.
+/// Plain values must come before tables for the TOML serializer.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    /// Address or profile nickname last connected to.
+    pub last_address: String,
+    pub volume: u32,
+    pub theme: Theme,
+    /// Seconds to wait for the server before giving up on connecting.
+    pub connect_timeout: u64,
+    /// Addresses connected to without a profile, most recent first.
+    pub recent_addresses: Vec<String>,
+    pub window: Option<WindowGeometry>,
+    pub profiles: Vec<Profile>,
+    pub hotkeys: Hotkeys,
+    pub notifications: NotificationSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            last_address: "spotifypi.local:9487".to_string(),
+            volume: 50,
+            theme: Theme::default(),
+            connect_timeout: 5,
+            recent_addresses: Vec::new(),
+            window: None,
+            profiles: Vec::new(),
+            hotkeys: Hotkeys::default(),
+            notifications: NotificationSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads `settings.toml`, migrating it from older versions. Without a
+    /// settings file, the shortcuts and notification settings are taken
+    /// from the separate files earlier versions used.
+    pub fn load() -> Self {
+        let path = match config_dir() {
+            Some(dir) => dir.join(SETTINGS_FILE),
+            None => return Self::default(),
+        };
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => return Self::from_legacy_files(),
+        };
+        let value: toml::Value = match toml::from_str(&text) {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("Failed to parse {}: {}", path.display(), e);
+                backup(&path);
+                return Self::default();
+            }
+        };
+
+        let version = value.get("version")
+            .and_then(|v| v.as_integer())
+            .unwrap_or(0) as u32;
+        if version > SETTINGS_VERSION {
+            warn!("{} is from a newer version ({}); unknown settings are ignored", path.display(), version);
+            backup(&path);
+        }
+        match migrate(value, version).try_into::<Settings>() {
+            Ok(mut settings) => {
+                settings.version = SETTINGS_VERSION;
+                settings
+            }
+            Err(e) => {
+                warn!("Failed to load {}: {}", path.display(), e);
+                backup(&path);
+                Self::default()
+            }
+        }
+    }
+
+    fn from_legacy_files() -> Self {
+        Self {
+            hotkeys: load_toml("hotkeys.toml"),
+            notifications: load_toml("notifications.toml"),
+            ..Self::default()
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_toml(SETTINGS_FILE, self)
+    }
+
+    pub fn profile(&self, nickname: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.nickname == nickname)
+    }
+
+    /// Remembers what was typed in the address box for the last connection.
+    /// Plain addresses also go to the history.
+    pub fn remember_address(&mut self, entry: &str) {
+        self.last_address = entry.to_string();
+        if self.profile(entry).is_none() {
+            self.recent_addresses.retain(|a| a != entry);
+            self.recent_addresses.insert(0, entry.to_string());
+            self.recent_addresses.truncate(MAX_RECENT_ADDRESSES);
+        }
+    }
+}
+
+/// Keeps a copy of a settings file that could not be fully loaded as
+/// `settings.toml.bak`, as the next save replaces it.
+fn backup(path: &Path) {
+    let backup_path = path.with_extension("toml.bak");
+    match std::fs::copy(path, &backup_path) {
+        Ok(_) => warn!("Kept a copy in {}", backup_path.display()),
+        Err(e) => warn!("Failed to back up {}: {}", path.display(), e),
+    }
+}
+
+/// Upgrades a settings file written by an older version, one version at a
+/// time.
+fn migrate(mut value: toml::Value, version: u32) -> toml::Value {
+    if version < 2 {
+        // Version 1 kept the history as `[[devices]]` tables with an
+        // `address` key.
+        if let Some(table) = value.as_table_mut() {
+            if let Some(toml::Value::Array(devices)) = table.remove("devices") {
+                let addresses = devices.iter()
+                    .filter_map(|d| d.get("address").cloned())
+                    .collect();
+                table.insert("recent_addresses".to_string(), toml::Value::Array(addresses));
+            }
+        }
+    }
+    value
+}
@@ -0,0 +1,105 @@
+use std::time::{Duration, Instant};
+
+use crate::my_enums::{SleepAction, SleepOption, TrackInfo};
+use crate::ramp::VolumeRamp;
+
+
+const FADE_DURATION: Duration = Duration::from_secs(120);
+
+pub enum SleepTimerStep {
+    Wait,
+    SetVolume(u32),
+    Finish(SleepAction),
+}
+
+pub struct SleepTimer {
+    action: SleepAction,
+    deadline: Option<Instant>,
+    track_id: Option<String>,
+    start_volume: u32,
+    fade: Option<VolumeRamp>,
+    last_volume: u32,
+}
+
+impl SleepTimer {
+    pub fn new(option: SleepOption, action: SleepAction, volume: u32, track: Option<&TrackInfo>) -> Option<Self> {
+        let mut timer = Self {
+            action,
+            deadline: None,
+            track_id: None,
+            start_volume: volume,
+            fade: None,
+            last_volume: volume,
+        };
+        match option {
+            SleepOption::Minutes(minutes) => {
+                timer.set_deadline(Instant::now() + Duration::from_secs(minutes as u64 * 60));
+            }
+            SleepOption::EndOfTrack => {
+                let track = track?;
+                timer.track_id = Some(track.id.clone());
+                timer.update_track_deadline(track);
+            }
+            SleepOption::Unknown => return None,
+        }
+        Some(timer)
+    }
+
+    pub fn start_volume(&self) -> u32 {
+        self.start_volume
+    }
+
+    /// Fires the end-of-track timer once the armed track stops playing.
+    pub fn on_track_change(&mut self, track: Option<&TrackInfo>) {
+        let armed_id = match &self.track_id {
+            Some(id) => id,
+            None => return,
+        };
+        match track {
+            Some(track) if &track.id == armed_id => self.update_track_deadline(track),
+            _ => self.deadline = Some(Instant::now()),
+        }
+    }
+
+    pub fn tick(&mut self, now: Instant) -> SleepTimerStep {
+        let deadline = match self.deadline {
+            Some(deadline) => deadline,
+            None => return SleepTimerStep::Wait,
+        };
+        if now >= deadline {
+            return SleepTimerStep::Finish(self.action);
+        }
+        if let Some(fade) = &self.fade {
+            let volume = fade.volume_at(now);
+            if volume != self.last_volume {
+                self.last_volume = volume;
+                return SleepTimerStep::SetVolume(volume);
+            }
+        }
+        SleepTimerStep::Wait
+    }
+
+    pub fn label(&self, now: Instant) -> String {
+        match self.deadline {
+            Some(deadline) => {
+                let secs = deadline.saturating_duration_since(now).as_secs();
+                format!("Sleep in {}:{:02}", secs / 60, secs % 60)
+            }
+            None => "Sleep at end of track".to_string(),
+        }
+    }
+
+    fn update_track_deadline(&mut self, track: &TrackInfo) {
+        if let (Some(duration), Some(progress)) = (track.duration_ms, track.progress_ms) {
+            let remaining = Duration::from_millis(duration.saturating_sub(progress));
+            self.set_deadline(Instant::now() + remaining);
+        }
+    }
+
+    fn set_deadline(&mut self, deadline: Instant) {
+        let now = Instant::now();
+        let fade_duration = FADE_DURATION.min(deadline.saturating_duration_since(now));
+        self.deadline = Some(deadline);
+        self.fade = Some(VolumeRamp::new(self.start_volume, 0, deadline - fade_duration, fade_duration));
+    }
+}
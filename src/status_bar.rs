@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use fltk::{prelude::*, *};
+
+use spotifypi_control_panel_fl::my_enums::CommandResult;
+
+use crate::theme;
+
+
+/// The row at the bottom of the main window: device, connection state,
+/// latency of the last reply and the last command with its result.
+pub struct StatusBar {
+    pub row: group::Flex,
+    frame_device: frame::Frame,
+    frame_state: frame::Frame,
+    frame_latency: frame::Frame,
+    frame_result: frame::Frame,
+}
+
+impl StatusBar {
+    pub fn new() -> Self {
+        let mut row = group::Flex::default().row();
+        let frame_device = Self::create_field();
+        let frame_state = Self::create_field();
+        let frame_latency = Self::create_field();
+        let frame_result = Self::create_field();
+        row.set_size(&frame_device, 200);
+        row.set_size(&frame_state, 150);
+        row.set_size(&frame_latency, 80);
+        row.set_pad(2);
+        row.end();
+
+        let mut status_bar = Self {
+            row,
+            frame_device,
+            frame_state,
+            frame_latency,
+            frame_result,
+        };
+        status_bar.set_state("Disconnected");
+        status_bar
+    }
+
+    fn create_field() -> frame::Frame {
+        let mut frame = frame::Frame::default()
+            .with_align(enums::Align::Inside | enums::Align::Left | enums::Align::Clip);
+        frame.set_frame(enums::FrameType::ThinDownBox);
+        frame.set_label_size(12);
+        frame.set_label_color(theme::text_color());
+        frame
+    }
+
+    fn set_field(frame: &mut frame::Frame, text: &str) {
+        // Leading space keeps the text off the box border; "@" would start
+        // a symbol.
+        frame.set_label(&format!(" {}", text.replace('@', "@@")));
+        frame.redraw();
+    }
+
+    pub fn set_device(&mut self, name: &str) {
+        Self::set_field(&mut self.frame_device, name);
+    }
+
+    pub fn set_state(&mut self, state: &str) {
+        self.frame_state.set_label_color(theme::text_color());
+        Self::set_field(&mut self.frame_state, state);
+    }
+
+    /// Like `set_state`, in red, for a failed connect or an invalid address.
+    pub fn set_error_state(&mut self, state: &str) {
+        self.frame_state.set_label_color(enums::Color::Red);
+        Self::set_field(&mut self.frame_state, state);
+    }
+
+    pub fn set_latency(&mut self, latency: Option<Duration>) {
+        let text = match latency {
+            Some(latency) => format!("{} ms", latency.as_millis()),
+            None => String::new(),
+        };
+        Self::set_field(&mut self.frame_latency, &text);
+    }
+
+    /// Shows e.g. "next_track failed", in red unless it succeeded.
+    pub fn set_last_command(&mut self, name: &str, result: CommandResult) {
+        let color = if result == CommandResult::Ok {
+            theme::text_color()
+        } else {
+            enums::Color::Red
+        };
+        self.frame_result.set_label_color(color);
+        Self::set_field(&mut self.frame_result, &format!("{} {}", name, result.as_str()));
+    }
+}
+
+impl Default for StatusBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use fltk::enums::Color;
+use fltk_theme::{SchemeType, ThemeType, WidgetScheme, WidgetTheme};
+use serde::{Deserialize, Serialize};
+
+
+static DARK: AtomicBool = AtomicBool::new(false);
+
+#[derive(Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    Fluent,
+    Aqua,
+    Gleam,
+    Dark,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [Theme::Fluent, Theme::Aqua, Theme::Gleam, Theme::Dark];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::Fluent => "fluent",
+            Theme::Aqua => "aqua",
+            Theme::Gleam => "gleam",
+            Theme::Dark => "dark",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|t| t.name() == name)
+    }
+
+    /// Must be called once, before any window is created.
+    pub fn apply(&self) {
+        match self {
+            Theme::Fluent => WidgetScheme::new(SchemeType::Fluent).apply(),
+            Theme::Aqua => WidgetScheme::new(SchemeType::Aqua).apply(),
+            Theme::Gleam => WidgetScheme::new(SchemeType::Gleam).apply(),
+            Theme::Dark => WidgetTheme::new(ThemeType::Dark).apply(),
+        }
+        DARK.store(*self == Theme::Dark, Ordering::Relaxed);
+    }
+}
+
+impl std::str::FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_name(s).ok_or_else(|| format!("unknown theme: {}", s))
+    }
+}
+
+fn is_dark() -> bool {
+    DARK.load(Ordering::Relaxed)
+}
+
+pub fn window_color() -> Color {
+    if is_dark() { Color::Background } else { Color::White }
+}
+
+pub fn button_color() -> Color {
+    if is_dark() { Color::Background } else { Color::from_rgb(225, 225, 225) }
+}
+
+pub fn text_color() -> Color {
+    if is_dark() { Color::Foreground } else { Color::Black }
+}
+
+/// Background and label color of the empty album art frame.
+pub fn placeholder_colors() -> (Color, Color) {
+    if is_dark() {
+        (Color::Background2, Color::Inactive)
+    } else {
+        (Color::from_rgb(235, 235, 235), Color::from_rgb(150, 150, 150))
+    }
+}
@@ -0,0 +1,327 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, Local};
+use fltk::{prelude::*, *};
+
+use spotifypi_control_panel_fl::my_enums::{FrameDirection, WSEventValue};
+use spotifypi_control_panel_fl::ws::convert_output_msg;
+
+use crate::my_dialogs::show_alert_dialog;
+use crate::theme;
+
+
+const MAX_ENTRIES: usize = 5000;
+/// Longer frames, such as now_playing with inline album art, are cut in
+/// the list. Exported files get them in full.
+const MAX_SHOWN_FRAME: usize = 200;
+const SHOW_CHOICES: [&str; 4] = ["All", "Sent", "Received", "Errors"];
+
+struct TrafficEntry {
+    time: DateTime<Local>,
+    /// `None` for connection status lines.
+    direction: Option<FrameDirection>,
+    text: String,
+    decoded: Option<WSEventValue>,
+    is_error: bool,
+}
+
+impl TrafficEntry {
+    fn line(&self, full: bool) -> String {
+        let arrow = match self.direction {
+            Some(FrameDirection::Sent) => "->",
+            Some(FrameDirection::Received) => "<-",
+            None => "--",
+        };
+        // One entry per line, so the buffer can be trimmed line by line.
+        let text = self.text.replace('\n', "\\n");
+        let text = if !full && text.chars().count() > MAX_SHOWN_FRAME {
+            let cut: String = text.chars().take(MAX_SHOWN_FRAME).collect();
+            format!("{}... ({} bytes)", cut, self.text.len())
+        } else {
+            text
+        };
+        let mut line = format!("{} {} {}", self.time.format("%H:%M:%S%.3f"), arrow, text);
+        if let Some(decoded) = &self.decoded {
+            line.push_str(&format!("  => {:?}", decoded));
+        }
+        if self.is_error {
+            line.push_str("  [error]");
+        }
+        line
+    }
+
+    /// `show` indexes `SHOW_CHOICES`; `filter` is matched case-insensitively
+    /// against the whole line.
+    fn matches(&self, show: i32, filter: &str) -> bool {
+        let shown = match show {
+            1 => self.direction == Some(FrameDirection::Sent),
+            2 => self.direction == Some(FrameDirection::Received),
+            3 => self.is_error,
+            _ => true,
+        };
+        shown && (filter.is_empty() || self.line(true).to_lowercase().contains(&filter.to_lowercase()))
+    }
+}
+
+
+/// Window listing the frames exchanged with the Pi. Frames are recorded
+/// only while it is shown; see `recording`.
+pub struct TrafficConsole {
+    win: window::Window,
+    buffer: text::TextBuffer,
+    display: text::TextDisplay,
+    input_filter: input::Input,
+    choice_show: menu::Choice,
+    button_pause: button::ToggleButton,
+    entries: Rc<RefCell<VecDeque<TrafficEntry>>>,
+    /// Lines in `buffer`, kept to scroll without counting them.
+    shown_lines: Rc<Cell<i32>>,
+    recording: Arc<AtomicBool>,
+}
+
+impl TrafficConsole {
+    pub fn new() -> Self {
+        let mut win = window::Window::default()
+            .with_size(760, 360)
+            .with_label("Protocol traffic");
+
+        let mut panel = group::Flex::default_fill().column();
+
+        let mut row_filter = group::Flex::default().row();
+        let label = frame::Frame::default()
+            .with_label("Filter:")
+            .with_align(enums::Align::Inside | enums::Align::Right);
+        let mut input_filter = input::Input::default();
+        let mut choice_show = menu::Choice::default();
+        for choice in SHOW_CHOICES {
+            choice_show.add_choice(choice);
+        }
+        choice_show.set_value(0);
+        row_filter.set_size(&label, 50);
+        row_filter.set_size(&choice_show, 100);
+        row_filter.end();
+
+        let buffer = text::TextBuffer::default();
+        let mut display = text::TextDisplay::default();
+        display.set_buffer(buffer.clone());
+        display.set_text_font(enums::Font::Courier);
+        display.set_text_size(12);
+
+        let mut row = group::Flex::default().row();
+        frame::Frame::default();
+        let mut button_pause = button::ToggleButton::default().with_label("Pause");
+        let mut button_clear = button::Button::default().with_label("Clear");
+        let mut button_export = button::Button::default().with_label("Export...");
+        row.set_size(&button_pause, 90);
+        row.set_size(&button_clear, 90);
+        row.set_size(&button_export, 90);
+        row.end();
+
+        panel.set_size(&row_filter, 30);
+        panel.set_size(&row, 30);
+        panel.end();
+
+        win.resizable(&panel);
+        win.set_color(theme::window_color());
+        win.end();
+
+        let entries = Rc::new(RefCell::new(VecDeque::new()));
+        let console = Self {
+            win: win.clone(),
+            buffer,
+            display,
+            input_filter: input_filter.clone(),
+            choice_show: choice_show.clone(),
+            button_pause: button_pause.clone(),
+            entries,
+            shown_lines: Rc::new(Cell::new(0)),
+            recording: Arc::new(AtomicBool::new(false)),
+        };
+
+        win.set_callback({
+            let recording = console.recording.clone();
+            move |win| {
+                recording.store(false, Ordering::Relaxed);
+                win.hide();
+            }
+        });
+
+        input_filter.set_trigger(enums::CallbackTrigger::Changed);
+        input_filter.set_callback({
+            let console = console.shallow_clone();
+            move |_| console.refresh()
+        });
+        choice_show.set_callback({
+            let console = console.shallow_clone();
+            move |_| console.refresh()
+        });
+        // Entries keep coming in while paused; they show up on resume.
+        button_pause.set_callback({
+            let console = console.shallow_clone();
+            move |_| console.refresh()
+        });
+        button_clear.set_callback({
+            let console = console.shallow_clone();
+            move |_| {
+                console.entries.borrow_mut().clear();
+                console.refresh();
+            }
+        });
+        button_export.set_callback({
+            let console = console.shallow_clone();
+            move |_| console.export()
+        });
+
+        console
+    }
+
+    /// Another handle to the same window and entries, for callbacks.
+    fn shallow_clone(&self) -> Self {
+        Self {
+            win: self.win.clone(),
+            buffer: self.buffer.clone(),
+            display: self.display.clone(),
+            input_filter: self.input_filter.clone(),
+            choice_show: self.choice_show.clone(),
+            button_pause: self.button_pause.clone(),
+            entries: self.entries.clone(),
+            shown_lines: self.shown_lines.clone(),
+            recording: self.recording.clone(),
+        }
+    }
+
+    pub fn show(&mut self) {
+        self.recording.store(true, Ordering::Relaxed);
+        self.win.show();
+    }
+
+    /// Set while the window is shown. Connections check it before passing
+    /// frames on, so nothing is copied or decoded for a closed window.
+    pub fn recording(&self) -> Arc<AtomicBool> {
+        self.recording.clone()
+    }
+
+    pub fn add_frame(&mut self, direction: FrameDirection, text: String) {
+        let decoded = match direction {
+            FrameDirection::Received => Some(convert_output_msg(text.clone())),
+            FrameDirection::Sent => None,
+        };
+        let is_error = decoded.as_ref().is_some_and(|event| event.is_error());
+        self.add(TrafficEntry {
+            time: Local::now(),
+            direction: Some(direction),
+            text,
+            decoded,
+            is_error,
+        });
+    }
+
+    /// Adds a connection status line, such as a failed connect.
+    pub fn add_status(&mut self, text: &str, is_error: bool) {
+        self.add(TrafficEntry {
+            time: Local::now(),
+            direction: None,
+            text: text.to_string(),
+            decoded: None,
+            is_error,
+        });
+    }
+
+    fn add(&mut self, entry: TrafficEntry) {
+        let paused = self.button_pause.is_toggled();
+        let show = self.choice_show.value();
+        let filter = self.input_filter.value();
+
+        let removed = {
+            let mut entries = self.entries.borrow_mut();
+            let removed = if entries.len() == MAX_ENTRIES { entries.pop_front() } else { None };
+            entries.push_back(entry);
+            removed
+        };
+        // While not paused, the buffer holds exactly the matching entries.
+        if paused {
+            return;
+        }
+        if removed.is_some_and(|removed| removed.matches(show, &filter)) {
+            if let Some(end) = self.buffer.find_char_forward(0, '\n') {
+                self.buffer.remove(0, end + 1);
+                self.shown_lines.set(self.shown_lines.get() - 1);
+            }
+        }
+        let entries = self.entries.borrow();
+        let entry = entries.back().expect("just added");
+        if entry.matches(show, &filter) {
+            let at_end = self.end_is_visible();
+            self.buffer.append(&format!("{}\n", entry.line(false)));
+            self.shown_lines.set(self.shown_lines.get() + 1);
+            if at_end {
+                self.scroll_to_end();
+            }
+        }
+    }
+
+    fn refresh(&self) {
+        if self.button_pause.is_toggled() {
+            return;
+        }
+        let mut buffer = self.buffer.clone();
+        let (lines, count) = self.shown_lines(false);
+        buffer.set_text(&lines);
+        self.shown_lines.set(count);
+        self.scroll_to_end();
+    }
+
+    /// Whether the view shows the end of the text, i.e. the user has not
+    /// scrolled up to read older frames.
+    fn end_is_visible(&self) -> bool {
+        // `position_to_xy` leaves positions outside the view at 0, 0.
+        let (_, y) = self.display.position_to_xy(self.buffer.length());
+        y != 0
+    }
+
+    fn scroll_to_end(&self) {
+        let mut display = self.display.clone();
+        display.scroll(self.shown_lines.get(), 0);
+    }
+
+    /// The entries matching the filter, as text and line count.
+    fn shown_lines(&self, full: bool) -> (String, i32) {
+        let show = self.choice_show.value();
+        let filter = self.input_filter.value();
+        let mut count = 0;
+        let text = self.entries.borrow().iter()
+            .filter(|entry| entry.matches(show, &filter))
+            .map(|entry| {
+                count += 1;
+                format!("{}\n", entry.line(full))
+            })
+            .collect();
+        (text, count)
+    }
+
+    /// Saves the entries matching the filter, with frames in full.
+    fn export(&self) {
+        let mut chooser = dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseSaveFile);
+        chooser.set_title("Export protocol traffic");
+        chooser.set_option(dialog::NativeFileChooserOptions::SaveAsConfirm);
+        chooser.set_preset_file("spotifypi-traffic.log");
+        chooser.show();
+        let path = chooser.filename();
+        if path.as_os_str().is_empty() {
+            return;
+        }
+        if let Err(e) = std::fs::write(&path, self.shown_lines(true).0) {
+            show_alert_dialog(&format!("Failed to export: {}", e));
+        }
+    }
+}
+
+impl Default for TrafficConsole {
+    fn default() -> Self {
+        Self::new()
+    }
+}
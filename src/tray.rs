@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use fltk::app;
+use tracing::warn;
+use tray_icon::icon::Icon;
+use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tray_icon::{ClickEvent, TrayEvent, TrayIcon, TrayIconBuilder};
+
+use crate::commands::Command;
+use crate::my_enums::MyAppMessage;
+
+
+const ICON_SIZE: u32 = 32;
+const VOLUME_PRESETS: [u32; 5] = [10, 25, 50, 75, 100];
+
+/// Tray icon with quick controls. The icon is green while connected.
+pub struct Tray {
+    icon: TrayIcon,
+    item_connect: MenuItem,
+    item_disconnect: MenuItem,
+    controls: Vec<MenuItem>,
+    submenu_volume: Submenu,
+}
+
+impl Tray {
+    /// Returns `None` when the desktop has no tray.
+    pub fn new(sender: app::Sender<MyAppMessage>) -> Option<Self> {
+        #[cfg(target_os = "linux")]
+        if let Err(e) = Self::init_gtk() {
+            warn!("Tray icon is not available: {}", e);
+            return None;
+        }
+
+        let mut actions: HashMap<u32, MyAppMessage> = HashMap::new();
+        let mut item = |text: &str, msg: MyAppMessage| {
+            let item = MenuItem::new(text, true, None);
+            actions.insert(item.id(), msg);
+            item
+        };
+        let item_show = item("Show window", MyAppMessage::ShowWindow);
+        let controls = vec![
+            item("Previous track", MyAppMessage::PrevTrack),
+            item("Play / Pause", MyAppMessage::PlayPause),
+            item("Next track", MyAppMessage::NextTrack),
+        ];
+        let volume_items: Vec<MenuItem> = VOLUME_PRESETS.iter()
+            .map(|volume| item(&format!("{}%", volume), MyAppMessage::SendCommand(Command::SetVolume(*volume))))
+            .collect();
+        let item_connect = item("Connect", MyAppMessage::ClickConnect);
+        let item_disconnect = item("Disconnect", MyAppMessage::ClickDisconnect);
+        let item_quit = item("Quit", MyAppMessage::Quit);
+
+        let submenu_volume = Submenu::new("Volume", true);
+        for volume_item in &volume_items {
+            submenu_volume.append(volume_item);
+        }
+        let menu = Menu::new();
+        menu.append(&item_show);
+        menu.append(&PredefinedMenuItem::separator());
+        for control in &controls {
+            menu.append(control);
+        }
+        menu.append(&submenu_volume);
+        menu.append(&PredefinedMenuItem::separator());
+        menu.append(&item_connect);
+        menu.append(&item_disconnect);
+        menu.append(&PredefinedMenuItem::separator());
+        menu.append(&item_quit);
+
+        MenuEvent::set_event_handler(Some({
+            let sender = sender.clone();
+            move |event: MenuEvent| {
+                if let Some(msg) = actions.get(&event.id) {
+                    sender.send(msg.clone());
+                    app::awake();
+                }
+            }
+        }));
+        TrayEvent::set_event_handler(Some(move |event: TrayEvent| {
+            if let ClickEvent::Double = event.event {
+                sender.send(MyAppMessage::ShowWindow);
+                app::awake();
+            }
+        }));
+
+        let icon = TrayIconBuilder::new()
+            .with_menu(Box::new(menu))
+            .with_icon(status_icon(false)?)
+            .with_tooltip("SpotifyPi Control Panel")
+            .build();
+        let icon = match icon {
+            Ok(icon) => icon,
+            Err(e) => {
+                warn!("Tray icon is not available: {}", e);
+                return None;
+            }
+        };
+
+        let mut tray = Self {
+            icon,
+            item_connect,
+            item_disconnect,
+            controls,
+            submenu_volume,
+        };
+        tray.set_connected(false);
+        Some(tray)
+    }
+
+    /// The tray menu is drawn by GTK on Linux, so its events are pumped from
+    /// the FLTK loop.
+    #[cfg(target_os = "linux")]
+    fn init_gtk() -> Result<(), String> {
+        gtk::init().map_err(|e| e.to_string())?;
+        app::add_timeout3(0.05, |handle| {
+            while gtk::events_pending() {
+                gtk::main_iteration_do(false);
+            }
+            app::repeat_timeout3(0.05, handle);
+        });
+        Ok(())
+    }
+
+    pub fn set_connected(&mut self, connected: bool) {
+        if let Some(icon) = status_icon(connected) {
+            let _ = self.icon.set_icon(Some(icon));
+        }
+        let tooltip = if connected { "SpotifyPi: connected" } else { "SpotifyPi: not connected" };
+        let _ = self.icon.set_tooltip(Some(tooltip));
+        self.item_connect.set_enabled(!connected);
+        self.item_disconnect.set_enabled(connected);
+        for control in &self.controls {
+            control.set_enabled(connected);
+        }
+        self.submenu_volume.set_enabled(connected);
+    }
+
+    /// While connecting, Disconnect cancels the attempt.
+    pub fn set_connecting(&mut self) {
+        let _ = self.icon.set_tooltip(Some("SpotifyPi: connecting..."));
+        self.item_connect.set_enabled(false);
+        self.item_disconnect.set_enabled(true);
+    }
+}
+
+/// A filled circle, green when connected and grey otherwise.
+fn status_icon(connected: bool) -> Option<Icon> {
+    let (r, g, b) = if connected { (30, 185, 84) } else { (150, 150, 150) };
+    let center = (ICON_SIZE as f32 - 1.0) / 2.0;
+    let radius = ICON_SIZE as f32 / 2.0 - 2.0;
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let distance = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt();
+            let alpha = (radius + 0.5 - distance).clamp(0.0, 1.0);
+            rgba.extend_from_slice(&[r, g, b, (alpha * 255.0) as u8]);
+        }
+    }
+    Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE).ok()
+}
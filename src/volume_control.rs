@@ -0,0 +1,74 @@
+use fltk::{prelude::*, *};
+
+use spotifypi_control_panel_fl::my_enums::MyAppMessage;
+
+use crate::theme;
+
+
+/// Volume slider with a numeric field next to it. Both stay in step and
+/// send `ChangeVolume` when the user changes either.
+pub struct VolumeControl {
+    pub row: group::Flex,
+    slider: valuator::HorNiceSlider,
+    input: valuator::ValueInput,
+}
+
+impl VolumeControl {
+    pub fn new(app_msg_sender: app::Sender<MyAppMessage>) -> Self {
+        let mut row = group::Flex::default().row();
+        let label = frame::Frame::default()
+            .with_label("Volume")
+            .with_align(enums::Align::Inside | enums::Align::Right);
+        let mut slider = valuator::HorNiceSlider::default();
+        slider.set_bounds(0.0, 100.0);
+        slider.set_step(1.0, 1);
+        slider.set_color(theme::button_color());
+        slider.visible_focus(false);
+        let mut input = valuator::ValueInput::default();
+        input.set_bounds(0.0, 100.0);
+        input.set_step(1.0, 1);
+        row.set_size(&label, 50);
+        row.set_size(&input, 40);
+        row.set_pad(4);
+        row.end();
+
+        slider.set_callback({
+            let mut input = input.clone();
+            let app_msg_sender = app_msg_sender.clone();
+            move |slider| {
+                input.set_value(slider.value());
+                app_msg_sender.send(MyAppMessage::ChangeVolume);
+            }
+        });
+        input.set_callback({
+            let mut slider = slider.clone();
+            move |input| {
+                let value = input.value().round().clamp(0.0, 100.0);
+                input.set_value(value);
+                slider.set_value(value);
+                app_msg_sender.send(MyAppMessage::ChangeVolume);
+            }
+        });
+
+        Self { row, slider, input }
+    }
+
+    pub fn value(&self) -> u32 {
+        self.slider.value() as u32
+    }
+
+    pub fn set_value(&mut self, volume: u32) {
+        self.slider.set_value(volume as f64);
+        self.input.set_value(volume as f64);
+    }
+
+    pub fn activate(&mut self) {
+        self.slider.activate();
+        self.input.activate();
+    }
+
+    pub fn deactivate(&mut self) {
+        self.slider.deactivate();
+        self.input.deactivate();
+    }
+}
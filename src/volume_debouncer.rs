@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+
+/// A change is sent once the volume has stayed put this long...
+const QUIET_PERIOD: Duration = Duration::from_millis(150);
+/// ...or, while it keeps changing, at most this often.
+const MIN_SEND_INTERVAL: Duration = Duration::from_millis(300);
+/// Replies to our changes that take longer are treated as changes from
+/// elsewhere.
+const ECHO_TIMEOUT: Duration = Duration::from_secs(2);
+
+
+/// Coalesces volume changes from the slider and shortcuts into fewer
+/// `set_volume` commands and recognises the server's replies to them.
+#[derive(Default)]
+pub struct VolumeDebouncer {
+    /// Latest change not sent yet, and when it was made.
+    pending: Option<(u32, Instant)>,
+    last_sent: Option<Instant>,
+    /// Sent volumes whose reply has not come back yet.
+    unanswered: VecDeque<(u32, Instant)>,
+    flush_scheduled: bool,
+}
+
+impl VolumeDebouncer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a change. Returns the volume to send right away when the
+    /// last one was sent long enough ago.
+    pub fn change(&mut self, volume: u32, now: Instant) -> Option<u32> {
+        self.pending = Some((volume, now));
+        if self.last_sent.is_none_or(|sent| now >= sent + MIN_SEND_INTERVAL) {
+            return self.send(now);
+        }
+        None
+    }
+
+    /// Records a volume the panel sets itself, e.g. for the sleep fade, and
+    /// which is sent right away. It replaces any change still pending.
+    pub fn set(&mut self, volume: u32, now: Instant) {
+        self.pending = None;
+        self.record(volume, now);
+    }
+
+    /// For the flush timer: returns the pending volume if it is due.
+    pub fn flush(&mut self, now: Instant) -> Option<u32> {
+        self.flush_scheduled = false;
+        let (_, changed) = self.pending?;
+        if now >= changed + QUIET_PERIOD
+            || self.last_sent.is_none_or(|sent| now >= sent + MIN_SEND_INTERVAL)
+        {
+            return self.send(now);
+        }
+        None
+    }
+
+    /// How long to wait before calling `flush`, if a change is pending and
+    /// no flush is scheduled yet.
+    pub fn schedule_flush(&mut self, now: Instant) -> Option<Duration> {
+        let (_, changed) = self.pending?;
+        if self.flush_scheduled {
+            return None;
+        }
+        self.flush_scheduled = true;
+        let quiet = changed + QUIET_PERIOD;
+        let due = match self.last_sent {
+            Some(sent) => quiet.min(sent + MIN_SEND_INTERVAL),
+            None => quiet,
+        };
+        Some(due.saturating_duration_since(now))
+    }
+
+    /// Whether a volume from the server is the reply to one of our changes,
+    /// or is stale because a newer change is still to be sent. Either way
+    /// it should not move the slider.
+    pub fn is_echo(&mut self, volume: u32, now: Instant) -> bool {
+        self.unanswered.retain(|(_, sent)| now < *sent + ECHO_TIMEOUT);
+        if let Some(idx) = self.unanswered.iter().position(|(sent, _)| *sent == volume) {
+            self.unanswered.drain(..=idx);
+            return true;
+        }
+        self.pending.is_some()
+    }
+
+    /// Forgets everything, e.g. on disconnect.
+    pub fn clear(&mut self) {
+        self.pending = None;
+        self.last_sent = None;
+        self.unanswered.clear();
+    }
+
+    fn send(&mut self, now: Instant) -> Option<u32> {
+        let (volume, _) = self.pending.take()?;
+        self.record(volume, now);
+        Some(volume)
+    }
+
+    fn record(&mut self, volume: u32, now: Instant) {
+        self.last_sent = Some(now);
+        self.unanswered.push_back((volume, now));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn first_change_is_sent_at_once() {
+        let mut debouncer = VolumeDebouncer::new();
+        assert_eq!(debouncer.change(40, Instant::now()), Some(40));
+    }
+
+    #[test]
+    fn sends_at_most_at_max_rate() {
+        let mut debouncer = VolumeDebouncer::new();
+        let start = Instant::now();
+        assert_eq!(debouncer.change(10, start), Some(10));
+        // Dragging the slider: every 50 ms a new value.
+        let mut sent = Vec::new();
+        for step in 1..=12u32 {
+            let now = start + ms(50 * step as u64);
+            sent.extend(debouncer.change(10 + step, now));
+        }
+        // 600 ms of changes after the first send: two more sends, no more.
+        assert_eq!(sent, vec![16, 22]);
+    }
+
+    #[test]
+    fn flushes_after_quiet_period() {
+        let mut debouncer = VolumeDebouncer::new();
+        let start = Instant::now();
+        assert_eq!(debouncer.change(10, start), Some(10));
+        assert_eq!(debouncer.change(20, start + ms(100)), None);
+        assert_eq!(debouncer.schedule_flush(start + ms(100)), Some(QUIET_PERIOD));
+        // Only one flush timer at a time.
+        assert_eq!(debouncer.schedule_flush(start + ms(120)), None);
+        assert_eq!(debouncer.flush(start + ms(200)), None);
+        assert_eq!(debouncer.flush(start + ms(250)), Some(20));
+        // Nothing left to send.
+        assert_eq!(debouncer.flush(start + ms(400)), None);
+        assert_eq!(debouncer.schedule_flush(start + ms(400)), None);
+    }
+
+    #[test]
+    fn flush_is_due_at_max_rate_while_changing() {
+        let mut debouncer = VolumeDebouncer::new();
+        let start = Instant::now();
+        debouncer.change(10, start);
+        debouncer.change(20, start + ms(250));
+        assert_eq!(debouncer.schedule_flush(start + ms(250)), Some(ms(50)));
+        debouncer.change(30, start + ms(280));
+        assert_eq!(debouncer.flush(start + ms(300)), Some(30));
+    }
+
+    #[test]
+    fn suppresses_echoes() {
+        let mut debouncer = VolumeDebouncer::new();
+        let start = Instant::now();
+        debouncer.change(10, start);
+        debouncer.change(20, start + ms(400));
+        assert!(debouncer.is_echo(10, start + ms(450)));
+        assert!(debouncer.is_echo(20, start + ms(500)));
+        // Each reply is only recognised once.
+        assert!(!debouncer.is_echo(20, start + ms(600)));
+        // A change from elsewhere.
+        assert!(!debouncer.is_echo(55, start + ms(700)));
+    }
+
+    #[test]
+    fn echoes_expire() {
+        let mut debouncer = VolumeDebouncer::new();
+        let start = Instant::now();
+        debouncer.change(10, start);
+        assert!(!debouncer.is_echo(10, start + ECHO_TIMEOUT));
+    }
+
+    #[test]
+    fn stale_volume_while_change_pending() {
+        let mut debouncer = VolumeDebouncer::new();
+        let start = Instant::now();
+        debouncer.change(10, start);
+        debouncer.change(20, start + ms(100));
+        assert!(debouncer.is_echo(70, start + ms(120)));
+    }
+
+    #[test]
+    fn set_replaces_pending_change() {
+        let mut debouncer = VolumeDebouncer::new();
+        let start = Instant::now();
+        debouncer.change(10, start);
+        debouncer.change(20, start + ms(100));
+        debouncer.set(5, start + ms(120));
+        assert_eq!(debouncer.flush(start + ms(500)), None);
+        assert!(debouncer.is_echo(5, start + ms(600)));
+        assert!(!debouncer.is_echo(20, start + ms(700)));
+    }
+}
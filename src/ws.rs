@@ -1,39 +1,166 @@
+use std::future::Future;
+use std::sync::Mutex;
+
 use futures_util::{future, pin_mut, StreamExt};
-use futures::channel::mpsc::UnboundedReceiver;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-use fltk::app::Sender;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout, Duration, Instant};
+use tokio::net::{lookup_host, TcpStream};
+use tokio_tungstenite::{client_async_tls, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::error::{Error as WsError, UrlError};
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
 use regex::Regex;
+use serde::Deserialize;
+use tracing::{debug, info, info_span, warn, Instrument};
+
+use crate::commands::Command;
+use crate::my_enums::{AlbumArt, CommandResult, ConnectError, FrameDirection, SceneStepReport, TrackInfo, WSEventValue};
+use crate::scenes::Scene;
+
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+/// How long `WsSession::close` waits for queued frames to go out.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
 
-use crate::my_enums::{MyAppMessage, WSEventValue};
+#[derive(Deserialize)]
+struct NowPlayingJson {
+    id: String,
+    title: String,
+    #[serde(default)]
+    artist: String,
+    #[serde(default)]
+    album: String,
+    #[serde(default)]
+    art_url: Option<String>,
+    #[serde(default)]
+    art_data: Option<String>,
+    #[serde(default)]
+    duration_ms: Option<u64>,
+    #[serde(default)]
+    progress_ms: Option<u64>,
+}
+
+
+/// Builds the handshake request for `url`, sending `token` as a bearer
+/// token when given.
+pub fn ws_request(url: &url::Url, token: Option<&str>) -> Result<Request, String> {
+    let mut request = url.as_str().into_client_request().map_err(|e| e.to_string())?;
+    if let Some(token) = token {
+        let value = HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|_| "Invalid characters in auth token".to_string())?;
+        request.headers_mut().insert("Authorization", value);
+    }
+    Ok(request)
+}
+
+/// Resolves the host, opens the TCP connection and does the WebSocket
+/// handshake, telling apart the ways this can fail.
+async fn open_ws(request: Request) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, ConnectError> {
+    let uri = request.uri();
+    let host = uri.host()
+        .ok_or_else(|| ConnectError::Other("No host in address".to_string()))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let is_tls = uri.scheme_str() == Some("wss");
+    let port = uri.port_u16().unwrap_or(if is_tls { 443 } else { 80 });
+
+    let addrs: Vec<_> = lookup_host((host.as_str(), port)).await
+        .map_err(|e| ConnectError::Dns(e.to_string()))?
+        .collect();
+    let mut last_error = None;
+    let mut stream = None;
+    for addr in addrs {
+        match TcpStream::connect(addr).await {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    let stream = match (stream, last_error) {
+        (Some(stream), _) => stream,
+        (None, Some(e)) => return Err(classify_io_error(e)),
+        (None, None) => return Err(ConnectError::Dns(format!("No addresses for {}", host))),
+    };
+
+    match client_async_tls(request, stream).await {
+        Ok((ws_stream, _)) => Ok(ws_stream),
+        Err(WsError::Http(response)) => Err(ConnectError::Rejected(response.status().as_u16())),
+        Err(WsError::Tls(e)) => Err(ConnectError::Tls(e.to_string())),
+        Err(WsError::Url(UrlError::TlsFeatureNotEnabled)) => Err(ConnectError::Tls("TLS is not supported".to_string())),
+        // rustls errors arrive wrapped in InvalidData I/O errors.
+        Err(WsError::Io(e)) if is_tls && e.kind() == std::io::ErrorKind::InvalidData => {
+            Err(ConnectError::Tls(e.to_string()))
+        }
+        Err(WsError::Io(e)) => Err(classify_io_error(e)),
+        Err(e) => Err(ConnectError::Other(e.to_string())),
+    }
+}
 
+fn classify_io_error(e: std::io::Error) -> ConnectError {
+    match e.kind() {
+        std::io::ErrorKind::ConnectionRefused => ConnectError::Refused,
+        std::io::ErrorKind::TimedOut => ConnectError::Timeout,
+        _ => ConnectError::Other(e.to_string()),
+    }
+}
 
-pub async fn connect_to_ws(url: url::Url, input_rx: UnboundedReceiver<Message>, output_tx: Sender<MyAppMessage>) {
-    let (ws_stream, _) = match connect_async(url).await {
+/// Runs a connection until it closes. `emit` receives every decoded event,
+/// `on_frame` the raw text of every sent and received frame. Connecting
+/// fails with `ConnectError::Timeout` after `connect_timeout`.
+pub async fn connect_to_ws<R, F, G>(request: R, connect_timeout: Duration, input_rx: UnboundedReceiver<Message>, emit: F, on_frame: G)
+where
+    R: IntoClientRequest + Unpin,
+    F: Fn(WSEventValue),
+    G: Fn(FrameDirection, &str),
+{
+    let opened = match request.into_client_request() {
+        Ok(request) => timeout(connect_timeout, open_ws(request)).await
+            .unwrap_or(Err(ConnectError::Timeout)),
+        Err(e) => Err(ConnectError::Other(e.to_string())),
+    };
+    let ws_stream = match opened {
         Ok(ws) => ws,
         Err(e) => {
-            eprintln!("Failed to connect: {}", e);
-            output_tx.send(MyAppMessage::WSEventValue(WSEventValue::Connect(false)));
+            warn!("Failed to connect: {}", e);
+            emit(WSEventValue::ConnectFailed(e));
             return
         }
     };
 
-    eprintln!("WebSocket handshake has been successfully completed");
-    output_tx.send(MyAppMessage::WSEventValue(WSEventValue::Connect(true)));
+    info!("WebSocket handshake has been successfully completed");
+    emit(WSEventValue::Connect);
 
     let (write, read) = ws_stream.split();
 
-    let input_to_ws = input_rx.map(Ok).forward(write);
+    let input_to_ws = input_rx
+        .inspect(|msg| {
+            if let Message::Text(text) = msg {
+                on_frame(FrameDirection::Sent, text);
+            }
+        })
+        .map(Ok)
+        .forward(write);
     let ws_to_output = {
         read.for_each(|message| async {
             match message {
                 Ok(msg) => {
                     let data = msg.into_data();
                     if let Ok(text) = String::from_utf8(data) {
+                        debug!("Received: {}", text);
+                        on_frame(FrameDirection::Received, &text);
                         let event_value= convert_output_msg(text);
-                        output_tx.send(MyAppMessage::WSEventValue(event_value));
+                        emit(event_value);
                     }
                 }
-                Err(err) => eprintln!("Message unwrap failed: {}", err)
+                Err(err) => warn!("Message unwrap failed: {}", err)
             }
         })
     };
@@ -41,11 +168,176 @@ pub async fn connect_to_ws(url: url::Url, input_rx: UnboundedReceiver<Message>,
     pin_mut!(input_to_ws, ws_to_output);
     future::select(input_to_ws, ws_to_output).await;
 
-    eprintln!("WebSocket disconnected !!!");
-    output_tx.send(MyAppMessage::WSEventValue(WSEventValue::Disconnect));
+    info!("WebSocket disconnected");
+    emit(WSEventValue::Disconnect);
+}
+
+/// A `connect_to_ws` connection running on its own task, with its events
+/// published on a broadcast channel. Used by the headless clients.
+pub struct WsSession {
+    input_tx: UnboundedSender<Message>,
+    events_tx: broadcast::Sender<WSEventValue>,
+    frames_tx: broadcast::Sender<String>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl WsSession {
+    /// Connects to `url`, giving up after `connect_timeout`.
+    pub async fn connect(url: url::Url, connect_timeout: Duration) -> Result<Self, ConnectError> {
+        let (input_tx, input_rx) = unbounded::<Message>();
+        let (events_tx, mut events) = broadcast::channel(256);
+        let (frames_tx, _) = broadcast::channel(256);
+        let span = info_span!("connection", %url);
+        let task = tokio::spawn({
+            let events_tx = events_tx.clone();
+            let frames_tx = frames_tx.clone();
+            async move {
+                connect_to_ws(url, connect_timeout, input_rx, |event_value| {
+                    let _ = events_tx.send(event_value);
+                }, |direction, frame| {
+                    if direction == FrameDirection::Received {
+                        let _ = frames_tx.send(frame.to_string());
+                    }
+                }).await;
+            }.instrument(span)
+        });
+
+        // `connect_to_ws` reports one or the other within `connect_timeout`.
+        let connected = loop {
+            match events.recv().await {
+                Ok(WSEventValue::Connect) => break Ok(()),
+                Ok(WSEventValue::ConnectFailed(e)) => break Err(e),
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break Err(ConnectError::Other("Connection task ended".to_string())),
+            }
+        };
+
+        connected.map(|()| Self { input_tx, events_tx, frames_tx, task: Mutex::new(Some(task)) })
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<WSEventValue> {
+        self.events_tx.subscribe()
+    }
+
+    pub fn subscribe_frames(&self) -> broadcast::Receiver<String> {
+        self.frames_tx.subscribe()
+    }
+
+    pub fn input_sender(&self) -> UnboundedSender<Message> {
+        self.input_tx.clone()
+    }
+
+    /// Sends `command` without waiting for a reply.
+    pub fn send(&self, command: &Command) -> bool {
+        self.input_tx.unbounded_send(Message::text(command.to_string())).is_ok()
+    }
+
+    /// Sends `command` and waits for its reply.
+    pub async fn request(&self, command: &Command) -> Result<Option<WSEventValue>, CommandResult> {
+        let mut events = self.subscribe();
+        request(command, &self.input_tx, &mut events).await
+    }
+
+    /// Sends a close frame after the frames already queued and waits for
+    /// the connection task to write them out.
+    pub async fn close(&self) {
+        let _ = self.input_tx.unbounded_send(Message::Close(None));
+        self.input_tx.close_channel();
+        let task = self.task.lock().unwrap().take();
+        if let Some(mut task) = task {
+            if timeout(CLOSE_TIMEOUT, &mut task).await.is_err() {
+                warn!("Connection did not close in time");
+                task.abort();
+            }
+        }
+    }
+}
+
+/// Connects to `url` over and over, backing off between attempts, and hands
+/// every attempt to `on_connect`, which should return once the session has
+/// disconnected.
+pub async fn keep_connected<F, Fut>(url: url::Url, connect_timeout: Duration, mut on_connect: F)
+where
+    F: FnMut(Result<WsSession, ConnectError>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut retry_delay = Duration::from_secs(1);
+    loop {
+        let result = WsSession::connect(url.clone(), connect_timeout).await;
+        if result.is_ok() {
+            retry_delay = Duration::from_secs(1);
+        }
+        on_connect(result).await;
+        sleep(retry_delay).await;
+        retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+    }
+}
+
+/// Sends the steps of `scene` one after another, waiting for each reply and
+/// reporting every step through `report`.
+pub async fn run_scene<F: Fn(SceneStepReport)>(scene: Scene, input_tx: UnboundedSender<Message>, mut events: broadcast::Receiver<WSEventValue>, report: F) {
+    let total = scene.steps.len();
+    for (idx, step) in scene.steps.into_iter().enumerate() {
+        if step.delay_ms > 0 {
+            sleep(Duration::from_millis(step.delay_ms)).await;
+        }
+        let result = send_command_and_wait(&step.command, &input_tx, &mut events).await;
+        report(SceneStepReport {
+            scene: scene.name.clone(),
+            step: idx + 1,
+            total,
+            command: step.command.to_string(),
+            result,
+        });
+        if result == CommandResult::Disconnected {
+            break;
+        }
+    }
+}
+
+/// Sends `command` and waits for the matching reply on `events`.
+pub async fn send_command_and_wait(command: &Command, input_tx: &UnboundedSender<Message>, events: &mut broadcast::Receiver<WSEventValue>) -> CommandResult {
+    match request(command, input_tx, events).await {
+        Ok(Some(reply)) if command.reply_result(&reply) == Some(false) => CommandResult::Failed,
+        Ok(_) => CommandResult::Ok,
+        Err(result) => result,
+    }
 }
 
-fn convert_output_msg(text: String) -> WSEventValue {
+/// Sends `command` and returns its reply event, or `None` for commands the
+/// server does not answer.
+pub async fn request(command: &Command, input_tx: &UnboundedSender<Message>, events: &mut broadcast::Receiver<WSEventValue>) -> Result<Option<WSEventValue>, CommandResult> {
+    while !matches!(events.try_recv(), Err(broadcast::error::TryRecvError::Empty) | Err(broadcast::error::TryRecvError::Closed)) {}
+
+    if input_tx.unbounded_send(Message::text(command.to_string())).is_err() {
+        return Err(CommandResult::Disconnected);
+    }
+    if !command.expects_reply() {
+        return Ok(None);
+    }
+
+    let deadline = Instant::now() + REPLY_TIMEOUT;
+    loop {
+        let event = match timeout(deadline.saturating_duration_since(Instant::now()), events.recv()).await {
+            Ok(Ok(event)) => event,
+            Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+            Ok(Err(broadcast::error::RecvError::Closed)) => return Err(CommandResult::Disconnected),
+            Err(_) => return Err(CommandResult::Timeout),
+        };
+        match event {
+            WSEventValue::Disconnect => return Err(CommandResult::Disconnected),
+            WSEventValue::Missing => return Err(CommandResult::Missing),
+            WSEventValue::NotFound => return Err(CommandResult::NotFound),
+            _ => {}
+        }
+        if command.reply_result(&event).is_some() {
+            return Ok(Some(event));
+        }
+    }
+}
+
+/// Decodes a frame received from the Pi.
+pub fn convert_output_msg(text: String) -> WSEventValue {
     let (event, value) = get_event_and_value_string(text);
     if event == "error" {
         if value == "missing" {
@@ -87,12 +379,55 @@ fn convert_output_msg(text: String) -> WSEventValue {
         } else {
             return WSEventValue::ToggleRepeatState(false);
         }
+    } else if event == "pause" {
+        return WSEventValue::Pause(value == "ok");
+    } else if event == "play" {
+        return WSEventValue::Play(value == "ok");
+    } else if event == "play_playlist" {
+        return WSEventValue::PlayPlaylist(value == "ok");
+    } else if event == "set_schedule" {
+        return WSEventValue::SetSchedule(value == "ok");
+    } else if event == "save_track" {
+        return WSEventValue::SaveTrack(value == "ok");
+    } else if event == "remove_saved_track" {
+        return WSEventValue::RemoveSavedTrack(value == "ok");
+    } else if event == "track_saved" {
+        return WSEventValue::TrackSaved(value == "true");
+    } else if event == "now_playing" {
+        return WSEventValue::NowPlaying(convert_now_playing(&value));
+    }
+    WSEventValue::Unknown
+}
+
+fn convert_now_playing(value: &str) -> Option<TrackInfo> {
+    if value.is_empty() {
+        return None;
     }
-    return WSEventValue::Unknown;
+    let json: NowPlayingJson = match serde_json::from_str(value) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("now_playing parse failed: {}", e);
+            return None;
+        }
+    };
+    let art = match (json.art_data, json.art_url) {
+        (Some(data), _) => base64::decode(data).ok().map(AlbumArt::Inline),
+        (None, Some(url)) => Some(AlbumArt::Url(url)),
+        (None, None) => None,
+    };
+    Some(TrackInfo {
+        id: json.id,
+        title: json.title,
+        artist: json.artist,
+        album: json.album,
+        art,
+        duration_ms: json.duration_ms,
+        progress_ms: json.progress_ms,
+    })
 }
 
 fn get_event_and_value_string(text: String) -> (String, String) {
-    let re = Regex::new(r"\[(?P<event>.+?)\]\((?P<value>.*?)\)").unwrap();
+    let re = Regex::new(r"(?s)^\s*\[(?P<event>.+?)\]\((?P<value>.*)\)\s*$").unwrap();
     match re.captures(&text) {
         Some(caps) => {
             let event = &caps["event"];